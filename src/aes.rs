@@ -207,7 +207,8 @@ impl<KS: AesKeySize> BlockCipher for Aes<KS> {
     }
 }
 
-trait AesKeySize: KeySize {
+/// The key sizes AES takes, for naming `Aes<KS>` generically.
+pub trait AesKeySize: KeySize {
     fn expand_key(_: Option<&Self>, key: &[u8]) -> SecureMem<Vec<u32>>;
     fn num_rounds(_: Option<&Self>) -> uint;
 }
@@ -253,21 +254,49 @@ impl AesKeySize for KeySize128 {
 }
 
 impl AesKeySize for KeySize192 {
-    fn expand_key(_: Option<&KeySize192>, _key: &[u8]) -> SecureMem<Vec<u32>> {
-        unimplemented!()
+    fn expand_key(_: Option<&KeySize192>, key: &[u8]) -> SecureMem<Vec<u32>> {
+        expand_key_generic(key, 6, 12)
     }
 
     fn num_rounds(_: Option<&KeySize192>) -> uint { 12 }
 }
 
 impl AesKeySize for KeySize256 {
-    fn expand_key(_: Option<&KeySize256>, _key: &[u8]) -> SecureMem<Vec<u32>> {
-        unimplemented!()
+    fn expand_key(_: Option<&KeySize256>, key: &[u8]) -> SecureMem<Vec<u32>> {
+        expand_key_generic(key, 8, 14)
     }
 
     fn num_rounds(_: Option<&KeySize256>) -> uint { 14 }
 }
 
+/// The straightforward key schedule from FIPS-197 section 5.2, used for the
+/// key sizes that don't have an unrolled version.
+#[allow(non_snake_case)]
+fn expand_key_generic(key: &[u8], Nk: uint, Nr: uint) -> SecureMem<Vec<u32>> {
+    assert!(key.len() == 4*Nk);
+    let mut out = SecureMem::new(Vec::from_elem(Nb*(Nr+1), 0u32));
+
+    {
+        let w = out.as_mut_slice();
+
+        for i in range(0, Nk) {
+            w[i] = unpack!(key, i);
+        }
+
+        for i in range(Nk, Nb * (Nr + 1)) {
+            let mut temp = w[i-1];
+            if i % Nk == 0 {
+                temp = sub_word(rot_word(temp)) ^ RCON[i/Nk - 1];
+            } else if Nk > 6 && i % Nk == 4 {
+                temp = sub_word(temp);
+            }
+            w[i] = w[i-Nk] ^ temp;
+        }
+    }
+
+    return out;
+}
+
 pub type Aes128 = Aes<KeySize128>;
 pub type Aes192 = Aes<KeySize192>;
 pub type Aes256 = Aes<KeySize256>;
@@ -535,3 +564,83 @@ fn test_cbc2() {
     let ptext = aes.decrypt_cbc(iv[], cipher[]);
     assert_eq!(ptext.as_slice(), plain.as_slice());
 }
+
+#[test]
+fn test_aes192_fips197() {
+    use serialize::hex::FromHex;
+    static KEY: &'static str = "000102030405060708090a0b0c0d0e0f1011121314151617";
+    static PLAIN: &'static str = "00112233445566778899aabbccddeeff";
+    static CIPHER: &'static str = "dda97ca4864cdfe06eaf70a0ec0d7191";
+    let (key, plain, cipher) = (KEY.from_hex().unwrap(),
+                                PLAIN.from_hex().unwrap(),
+                                CIPHER.from_hex().unwrap());
+
+    let aes: Aes192 = Aes::new(key[]).unwrap();
+
+    let ctext = aes.encrypt_ecb(plain[]);
+    assert_eq!(ctext.as_slice(), cipher.as_slice());
+
+    let ptext = aes.decrypt_ecb(cipher[]);
+    assert_eq!(ptext.as_slice(), plain.as_slice());
+}
+
+#[test]
+fn test_aes256_fips197() {
+    use serialize::hex::FromHex;
+    static KEY: &'static str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    static PLAIN: &'static str = "00112233445566778899aabbccddeeff";
+    static CIPHER: &'static str = "8ea2b7ca516745bfeafc49904b496089";
+    let (key, plain, cipher) = (KEY.from_hex().unwrap(),
+                                PLAIN.from_hex().unwrap(),
+                                CIPHER.from_hex().unwrap());
+
+    let aes: Aes256 = Aes::new(key[]).unwrap();
+
+    let ctext = aes.encrypt_ecb(plain[]);
+    assert_eq!(ctext.as_slice(), cipher.as_slice());
+
+    let ptext = aes.decrypt_ecb(cipher[]);
+    assert_eq!(ptext.as_slice(), plain.as_slice());
+}
+
+#[test]
+fn test_aes192_aesavs() {
+    use serialize::hex::FromHex;
+    // (key, plaintext, ciphertext) from the AESAVS GFSbox and VarKey tables
+    static VECTORS: &'static [(&'static str, &'static str, &'static str)] = &[
+        ("000000000000000000000000000000000000000000000000",
+         "1b077a6af4b7f98229de786d7516b639", "275cfc0413d8ccb70513c3859b1d0f72"),
+        ("fffffffffffffffffffffffffffffffffffffffffffffffc",
+         "00000000000000000000000000000000", "5eb9bc759e2ad8d2140a6c762ae9e1ab"),
+    ];
+
+    for &(k, p, c) in VECTORS.iter() {
+        let (key, plain, cipher) = (k.from_hex().unwrap(),
+                                    p.from_hex().unwrap(),
+                                    c.from_hex().unwrap());
+        let aes: Aes192 = Aes::new(key[]).unwrap();
+        assert_eq!(aes.encrypt_ecb(plain[]).as_slice(), cipher.as_slice());
+        assert_eq!(aes.decrypt_ecb(cipher[]).as_slice(), plain.as_slice());
+    }
+}
+
+#[test]
+fn test_aes256_aesavs() {
+    use serialize::hex::FromHex;
+    // (key, plaintext, ciphertext) from the AESAVS GFSbox and VarKey tables
+    static VECTORS: &'static [(&'static str, &'static str, &'static str)] = &[
+        ("0000000000000000000000000000000000000000000000000000000000000000",
+         "014730f80ac625fe84f026c60bfd547d", "5c9d844ed46f9885085e5d6a4f94c7d7"),
+        ("fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffc",
+         "00000000000000000000000000000000", "27936bd27fb1468fc8b48bc483321725"),
+    ];
+
+    for &(k, p, c) in VECTORS.iter() {
+        let (key, plain, cipher) = (k.from_hex().unwrap(),
+                                    p.from_hex().unwrap(),
+                                    c.from_hex().unwrap());
+        let aes: Aes256 = Aes::new(key[]).unwrap();
+        assert_eq!(aes.encrypt_ecb(plain[]).as_slice(), cipher.as_slice());
+        assert_eq!(aes.decrypt_ecb(cipher[]).as_slice(), plain.as_slice());
+    }
+}
@@ -0,0 +1,542 @@
+use HashFn;
+use blake2::Blake2b;
+use constanttime::ConstantTimeEq;
+use securemem::SecureMem;
+
+use serialize::base64::{Config, FromBase64, ToBase64, STANDARD};
+use std::from_str::from_str;
+use std::slice::bytes;
+use std::sync::Arc;
+
+/// Words in a 1 KiB memory block.
+const BLOCK_WORDS: uint = 128;
+/// Each lane is split into this many segments, and lanes synchronize
+/// after every one.
+const SYNC_POINTS: uint = 4;
+const VERSION: u32 = 0x13;
+
+/// Argon2d indexes memory by the data, Argon2i independently of it, and
+/// Argon2id does the latter for the first half pass and the former after.
+#[deriving(Clone, PartialEq, Show)]
+pub enum Variant {
+    Argon2d,
+    Argon2i,
+    Argon2id,
+}
+
+impl Variant {
+    fn type_id(&self) -> u32 {
+        match *self {
+            Variant::Argon2d => 0,
+            Variant::Argon2i => 1,
+            Variant::Argon2id => 2,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Variant::Argon2d => "argon2d",
+            Variant::Argon2i => "argon2i",
+            Variant::Argon2id => "argon2id",
+        }
+    }
+}
+
+/// Argon2 (RFC 9106, version 0x13) with a fixed choice of variant and
+/// costs. Memory is `m_cost` KiB, `t_cost` is the number of passes over
+/// it, and each of the `lanes` is filled on its own task.
+pub struct Argon2 {
+    variant: Variant,
+    m_cost: u32,
+    t_cost: u32,
+    lanes: u32,
+}
+
+/// The memory layout that follows from the costs, in blocks.
+#[deriving(Clone)]
+struct Geometry {
+    variant: Variant,
+    lanes: uint,
+    lane_len: uint,
+    seg_len: uint,
+    passes: uint,
+    blocks: uint,
+}
+
+// `bytes::copy_memory` only takes bytes.
+fn copy_block(dst: &mut [u64], src: &[u64]) {
+    for (d, &s) in dst.iter_mut().zip(src.iter()) {
+        *d = s;
+    }
+}
+
+fn permute(v: &mut [u64, ..16]) {
+    argon2_gb!(v, 0, 4, 8, 12);
+    argon2_gb!(v, 1, 5, 9, 13);
+    argon2_gb!(v, 2, 6, 10, 14);
+    argon2_gb!(v, 3, 7, 11, 15);
+    argon2_gb!(v, 0, 5, 10, 15);
+    argon2_gb!(v, 1, 6, 11, 12);
+    argon2_gb!(v, 2, 7, 8, 13);
+    argon2_gb!(v, 3, 4, 9, 14);
+}
+
+/// The compression function G. With `with_xor` the result is XORed into
+/// `next` rather than replacing it, as every pass after the first does.
+fn fill_block(prev: &[u64], refb: &[u64], next: &mut [u64], with_xor: bool) {
+    let mut r = [0u64, ..BLOCK_WORDS];
+    for i in range(0u, BLOCK_WORDS) {
+        r[i] = prev[i] ^ refb[i];
+    }
+    let mut q = r;
+    let mut v = [0u64, ..16];
+
+    // The block is an 8x8 matrix of 16-byte registers; permute the rows,
+    // then the columns.
+    for i in range(0u, 8) {
+        for j in range(0u, 16) {
+            v[j] = q[16*i + j];
+        }
+        permute(&mut v);
+        for j in range(0u, 16) {
+            q[16*i + j] = v[j];
+        }
+    }
+    for i in range(0u, 8) {
+        for j in range(0u, 8) {
+            v[2*j] = q[16*j + 2*i];
+            v[2*j + 1] = q[16*j + 2*i + 1];
+        }
+        permute(&mut v);
+        for j in range(0u, 8) {
+            q[16*j + 2*i] = v[2*j];
+            q[16*j + 2*i + 1] = v[2*j + 1];
+        }
+    }
+
+    for i in range(0u, BLOCK_WORDS) {
+        if with_xor {
+            next[i] ^= r[i] ^ q[i];
+        } else {
+            next[i] = r[i] ^ q[i];
+        }
+    }
+}
+
+/// The variable-length hash H', filling all of `out` from the
+/// concatenation of `input`.
+fn hprime(out: &mut [u8], input: &[&[u8]]) {
+    let mut len = [0u8, ..4];
+    store_32l!(out.len() as u32, len[mut]);
+
+    if out.len() <= 64 {
+        let mut h = Blake2b::new(out.len()).unwrap();
+        h.update(&len);
+        for x in input.iter() {
+            h.update(*x);
+        }
+        bytes::copy_memory(out, h.digest()[]);
+        return;
+    }
+
+    let mut h = Blake2b::new(64).unwrap();
+    h.update(&len);
+    for x in input.iter() {
+        h.update(*x);
+    }
+    let mut v = SecureMem::new(h.digest());
+    bytes::copy_memory(out[mut ..32], v.slice_to(32));
+    let mut pos = 32;
+
+    while out.len() - pos > 64 {
+        let mut h = Blake2b::new(64).unwrap();
+        h.update(v.as_slice());
+        v = SecureMem::new(h.digest());
+        bytes::copy_memory(out[mut pos..pos + 32], v.slice_to(32));
+        pos += 32;
+    }
+
+    let mut h = Blake2b::new(out.len() - pos).unwrap();
+    h.update(v.as_slice());
+    bytes::copy_memory(out[mut pos..], h.digest()[]);
+}
+
+/// Computes one segment of one lane, reading the rest of memory from
+/// `mem`. Returns the new contents of the segment.
+fn fill_segment(mem: &[u64], g: &Geometry, pass: uint, slice: uint, lane: uint) -> Vec<u64> {
+    let seg_first = slice * g.seg_len;
+    let seg_start = (lane * g.lane_len + seg_first) * BLOCK_WORDS;
+    let mut seg = mem[seg_start..seg_start + g.seg_len * BLOCK_WORDS].to_vec();
+
+    let data_independent = match g.variant {
+        Variant::Argon2d => false,
+        Variant::Argon2i => true,
+        Variant::Argon2id => pass == 0 && slice < SYNC_POINTS / 2,
+    };
+    let zero = [0u64, ..BLOCK_WORDS];
+    let mut input = [0u64, ..BLOCK_WORDS];
+    let mut address = [0u64, ..BLOCK_WORDS];
+    let mut tmp = [0u64, ..BLOCK_WORDS];
+    input[0] = pass as u64;
+    input[1] = lane as u64;
+    input[2] = slice as u64;
+    input[3] = g.blocks as u64;
+    input[4] = g.passes as u64;
+    input[5] = g.variant.type_id() as u64;
+
+    // The first two blocks of each lane are already there.
+    let start = if pass == 0 && slice == 0 { 2 } else { 0 };
+    let mut prev = [0u64, ..BLOCK_WORDS];
+    let mut refb = [0u64, ..BLOCK_WORDS];
+
+    for idx in range(start, g.seg_len) {
+        if idx == 0 {
+            let p = (lane * g.lane_len + (seg_first + g.lane_len - 1) % g.lane_len) * BLOCK_WORDS;
+            copy_block(prev[mut], mem[p..p + BLOCK_WORDS]);
+        } else {
+            copy_block(prev[mut], seg[(idx - 1) * BLOCK_WORDS..idx * BLOCK_WORDS]);
+        }
+
+        let rand = if data_independent {
+            if idx % BLOCK_WORDS == 0 || idx == start {
+                input[6] += 1;
+                fill_block(&zero, &input, tmp[mut], false);
+                fill_block(&zero, &tmp, address[mut], false);
+            }
+            address[idx % BLOCK_WORDS]
+        } else {
+            prev[0]
+        };
+
+        let ref_lane = if pass == 0 && slice == 0 {
+            lane
+        } else {
+            ((rand >> 32) % g.lanes as u64) as uint
+        };
+        let same_lane = ref_lane == lane;
+
+        // How many blocks the reference may be chosen from, and where that
+        // window starts within the lane.
+        let area = if pass == 0 {
+            if slice == 0 {
+                idx - 1
+            } else if same_lane {
+                seg_first + idx - 1
+            } else {
+                seg_first - if idx == 0 { 1 } else { 0 }
+            }
+        } else if same_lane {
+            g.lane_len - g.seg_len + idx - 1
+        } else {
+            g.lane_len - g.seg_len - if idx == 0 { 1 } else { 0 }
+        };
+        let window_start = if pass == 0 || slice == SYNC_POINTS - 1 {
+            0
+        } else {
+            seg_first + g.seg_len
+        };
+
+        let j1 = rand & 0xffffffff;
+        let x = (j1 * j1) >> 32;
+        let rel = area - 1 - ((area as u64 * x) >> 32) as uint;
+        let ref_index = (window_start + rel) % g.lane_len;
+
+        if same_lane && ref_index >= seg_first && ref_index < seg_first + g.seg_len {
+            let r = (ref_index - seg_first) * BLOCK_WORDS;
+            copy_block(refb[mut], seg[r..r + BLOCK_WORDS]);
+        } else {
+            let r = (ref_lane * g.lane_len + ref_index) * BLOCK_WORDS;
+            copy_block(refb[mut], mem[r..r + BLOCK_WORDS]);
+        }
+
+        fill_block(&prev, &refb, seg[mut idx * BLOCK_WORDS..(idx + 1) * BLOCK_WORDS], pass > 0);
+    }
+
+    for w in prev.iter_mut().chain(refb.iter_mut()) {
+        *w = 0;
+    }
+    seg
+}
+
+fn store_segment(mem: &mut [u64], g: &Geometry, slice: uint, lane: uint, seg: &[u64]) {
+    let start = (lane * g.lane_len + slice * g.seg_len) * BLOCK_WORDS;
+    copy_block(mem[mut start..start + seg.len()], seg);
+}
+
+fn fill_memory(memory: &mut Arc<Vec<u64>>, g: &Geometry) {
+    for pass in range(0, g.passes) {
+        for slice in range(0, SYNC_POINTS) {
+            if g.lanes == 1 {
+                let seg = SecureMem::new(fill_segment(memory.as_slice(), g, pass, slice, 0));
+                store_segment(memory.make_unique().as_mut_slice(), g, slice, 0, seg.as_slice());
+                continue;
+            }
+
+            let (tx, rx) = channel();
+            for lane in range(0, g.lanes) {
+                let (tx, mem, g) = (tx.clone(), memory.clone(), g.clone());
+                spawn(proc() {
+                    let seg = fill_segment(mem.as_slice(), &g, pass, slice, lane);
+                    // Let go of the memory before reporting back, so the
+                    // caller can write to it without copying it.
+                    drop(mem);
+                    tx.send((lane, seg));
+                });
+            }
+
+            let mut segments = Vec::with_capacity(g.lanes);
+            for _ in range(0, g.lanes) {
+                let (lane, seg) = rx.recv();
+                segments.push((lane, SecureMem::new(seg)));
+            }
+            let mem = memory.make_unique();
+            for &(lane, ref seg) in segments.iter() {
+                store_segment(mem.as_mut_slice(), g, slice, lane, seg.as_slice());
+            }
+        }
+    }
+}
+
+fn b64_config() -> Config {
+    Config { pad: false, ..STANDARD }
+}
+
+fn cost_param(s: &str, prefix: &str) -> Option<u32> {
+    if s.starts_with(prefix) {
+        from_str(s[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+impl Argon2 {
+    /// Returns None unless there's at least one pass, between 1 and
+    /// 2^24 - 1 lanes, and at least 8 KiB of memory per lane.
+    pub fn new(variant: Variant, m_cost: u32, t_cost: u32, lanes: u32) -> Option<Argon2> {
+        if t_cost == 0 || lanes == 0 || lanes > 0xffffff || (m_cost as u64) < 8 * lanes as u64 {
+            return None;
+        }
+        Some(Argon2 {
+            variant: variant,
+            m_cost: m_cost,
+            t_cost: t_cost,
+            lanes: lanes,
+        })
+    }
+
+    /// Hashes `password` into a `tag_len`-byte tag. `secret` and `ad` may be
+    /// empty. Returns None if the salt is shorter than 8 bytes or the tag
+    /// shorter than 4.
+    pub fn hash(&self, password: &[u8], salt: &[u8], secret: &[u8], ad: &[u8],
+                tag_len: uint) -> Option<Vec<u8>> {
+        if salt.len() < 8 || tag_len < 4 {
+            return None;
+        }
+
+        let lanes = self.lanes as uint;
+        let seg_len = self.m_cost as uint / (lanes * SYNC_POINTS);
+        let g = Geometry {
+            variant: self.variant.clone(),
+            lanes: lanes,
+            lane_len: seg_len * SYNC_POINTS,
+            seg_len: seg_len,
+            passes: self.t_cost as uint,
+            blocks: seg_len * SYNC_POINTS * lanes,
+        };
+
+        let mut word = [0u8, ..4];
+        let mut h = Blake2b::new(64).unwrap();
+        for &w in [self.lanes, tag_len as u32, self.m_cost, self.t_cost,
+                   VERSION, self.variant.type_id()].iter() {
+            store_32l!(w, word[mut]);
+            h.update(&word);
+        }
+        for x in [password, salt, secret, ad].iter() {
+            store_32l!(x.len() as u32, word[mut]);
+            h.update(&word);
+            h.update(*x);
+        }
+        let h0 = SecureMem::new(h.digest());
+
+        let mut memory = Arc::new(Vec::from_elem(g.blocks * BLOCK_WORDS, 0u64));
+        {
+            let mem = memory.make_unique();
+            let mut block = SecureMem::new(Vec::from_elem(BLOCK_WORDS * 8, 0u8));
+            let mut lane_word = [0u8, ..4];
+            for lane in range(0, lanes) {
+                store_32l!(lane as u32, lane_word[mut]);
+                for i in range(0u, 2) {
+                    store_32l!(i as u32, word[mut]);
+                    hprime(block.as_mut_slice(), &[h0.as_slice(), word[], lane_word[]]);
+                    let (base, b) = ((lane * g.lane_len + i) * BLOCK_WORDS, block.as_slice());
+                    for w in range(0u, BLOCK_WORDS) {
+                        mem[base + w] = get_64l!(b, w);
+                    }
+                }
+            }
+        }
+
+        fill_memory(&mut memory, &g);
+
+        let mut tag = Vec::from_elem(tag_len, 0u8);
+        {
+            let mem = memory.make_unique();
+            let mut c = [0u64, ..BLOCK_WORDS];
+            for lane in range(0, lanes) {
+                let base = (lane * g.lane_len + g.lane_len - 1) * BLOCK_WORDS;
+                for w in range(0u, BLOCK_WORDS) {
+                    c[w] ^= mem[base + w];
+                }
+            }
+            let mut block = SecureMem::new(Vec::from_elem(BLOCK_WORDS * 8, 0u8));
+            for w in range(0u, BLOCK_WORDS) {
+                store_64l!(c[w], block.slice_from_mut(8 * w));
+                c[w] = 0;
+            }
+            hprime(tag[mut], &[block.as_slice()]);
+
+            for w in mem.iter_mut() {
+                *w = 0;
+            }
+        }
+
+        Some(tag)
+    }
+
+    /// Hashes `password` with a 32-byte tag and encodes the result as a PHC
+    /// string, e.g. `$argon2id$v=19$m=65536,t=3,p=4$<salt>$<hash>`. A
+    /// secret isn't recorded in the string; the same one has to be passed
+    /// to `verify_encoded`.
+    pub fn hash_encoded(&self, password: &[u8], salt: &[u8], secret: &[u8]) -> Option<String> {
+        let tag = match self.hash(password, salt, secret, &[], 32) {
+            Some(tag) => tag,
+            None => return None,
+        };
+        Some(format!("${}$v={}$m={},t={},p={}${}${}", self.variant.name(), VERSION,
+                     self.m_cost, self.t_cost, self.lanes,
+                     salt.to_base64(b64_config()), tag[].to_base64(b64_config())))
+    }
+}
+
+/// Checks `password` against a PHC string from `Argon2::hash_encoded`,
+/// comparing the tags in constant time. Malformed strings never verify, and
+/// neither do ones asking for more than `max_m_cost` KiB of memory, so a
+/// stored string can't make the check allocate whatever it likes.
+pub fn verify_encoded(encoded: &str, password: &[u8], secret: &[u8], max_m_cost: u32) -> bool {
+    let parts: Vec<&str> = encoded.split('$').collect();
+    if parts.len() != 6 || parts[0] != "" || parts[2] != "v=19" {
+        return false;
+    }
+
+    let variant = match parts[1] {
+        "argon2d" => Variant::Argon2d,
+        "argon2i" => Variant::Argon2i,
+        "argon2id" => Variant::Argon2id,
+        _ => return false,
+    };
+
+    let costs: Vec<&str> = parts[3].split(',').collect();
+    if costs.len() != 3 {
+        return false;
+    }
+    let argon2 = match (cost_param(costs[0], "m="), cost_param(costs[1], "t="),
+                        cost_param(costs[2], "p=")) {
+        (Some(m), _, _) if m > max_m_cost => return false,
+        (Some(m), Some(t), Some(p)) => match Argon2::new(variant, m, t, p) {
+            Some(argon2) => argon2,
+            None => return false,
+        },
+        _ => return false,
+    };
+
+    let (salt, expected) = match (parts[4].from_base64(), parts[5].from_base64()) {
+        (Ok(salt), Ok(expected)) => (salt, expected),
+        _ => return false,
+    };
+
+    match argon2.hash(password, salt[], secret, &[], expected.len()) {
+        Some(tag) => tag[].const_time_eq(&expected[]),
+        None => false,
+    }
+}
+
+#[test]
+fn test_argon2_rfc9106() {
+    use serialize::hex::ToHex;
+    let (password, salt) = ([1u8, ..32], [2u8, ..16]);
+    let (secret, ad) = ([3u8, ..8], [4u8, ..12]);
+
+    let check = |variant: Variant, expected: &str| {
+        let argon2 = Argon2::new(variant, 32, 3, 4).unwrap();
+        let tag = argon2.hash(&password, &salt, &secret, &ad, 32).unwrap();
+        assert_eq!(tag[].to_hex()[], expected);
+    };
+    check(Variant::Argon2d, "512b391b6f1162975371d30919734294f868e3be3984f3c1a13a4db9fabe4acb");
+    check(Variant::Argon2i, "c814d9d1dc7f37aa13f0d77f2494bda1c8de6b016dd388d29952a4c4672b6ce8");
+    check(Variant::Argon2id, "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659");
+}
+
+#[test]
+fn test_argon2_more_blocks() {
+    use serialize::hex::ToHex;
+    // A single lane long enough to need several Argon2i address blocks per
+    // segment.
+    static VECTORS: &'static [(Variant, &'static str)] = &[
+        (Variant::Argon2d, "ed6969800b5e64101404df1fb029419aa8c02b4c25654ae59f655a8b581424ab"),
+        (Variant::Argon2i, "fc0a265b35c6b8ee7a421997137a7dff447cd550f04d4683d7140c906c3e21b4"),
+        (Variant::Argon2id, "ec57ec9c0eaf51eeea2e92ffdcaa9cdee478f1927215b515b7b8d66657f41ed9"),
+    ];
+    for &(ref variant, expected) in VECTORS.iter() {
+        let argon2 = Argon2::new(variant.clone(), 1024, 2, 1).unwrap();
+        let tag = argon2.hash(b"password", b"somesalt", &[], &[], 32).unwrap();
+        assert_eq!(tag[].to_hex()[], expected);
+    }
+}
+
+#[test]
+fn test_argon2_long_tag() {
+    use serialize::hex::ToHex;
+    // Tags over 64 bytes go through the chained form of H'.
+    let argon2 = Argon2::new(Variant::Argon2id, 64, 1, 2).unwrap();
+    let tag = argon2.hash(b"password", b"somesalt", &[], &[], 100).unwrap();
+    assert_eq!(tag[].to_hex()[],
+               "c47496aa694afe4064653d34f2091f93a27c82217a0c2ea9552506f8e31b6f66\
+                7839bc0229e7619b020f1308fb3b7a357f99804cdffb785fbc7a9163b6dd49ca\
+                d14febaeb9a6641500fe9412c37060c9a2a99328c5a9df2e06b0a5372385a389\
+                fd9fa21b");
+}
+
+#[test]
+fn test_argon2_phc() {
+    let argon2 = Argon2::new(Variant::Argon2id, 256, 2, 2).unwrap();
+    let encoded = argon2.hash_encoded(b"hunter2", b"saltsaltsaltsalt", &[]).unwrap();
+    assert_eq!(encoded[],
+               "$argon2id$v=19$m=256,t=2,p=2$c2FsdHNhbHRzYWx0c2FsdA\
+                $EZnpBfai4F8N6f4QFXUKJe7iG10lquFZ2HHlCqMXQFs");
+
+    assert!(verify_encoded(encoded[], b"hunter2", &[], 256));
+    assert!(!verify_encoded(encoded[], b"hunter3", &[], 256));
+    assert!(!verify_encoded(encoded[], b"hunter2", b"pepper", 256));
+    assert!(!verify_encoded(encoded[].replace("argon2id", "argon2i")[], b"hunter2", &[], 256));
+    assert!(!verify_encoded("$argon2id$v=19$m=256,t=2$c2FsdHNhbHRzYWx0c2FsdA$AAAA",
+                            b"hunter2", &[], 256));
+    assert!(!verify_encoded("", b"hunter2", &[], 256));
+
+    // More memory than the caller allows fails before anything is allocated.
+    assert!(!verify_encoded(encoded[], b"hunter2", &[], 255));
+    assert!(!verify_encoded("$argon2id$v=19$m=4294967295,t=2,p=2$c2FsdHNhbHRzYWx0c2FsdA\
+                             $EZnpBfai4F8N6f4QFXUKJe7iG10lquFZ2HHlCqMXQFs",
+                            b"hunter2", &[], 65536));
+}
+
+#[test]
+fn test_argon2_bad_params() {
+    assert!(Argon2::new(Variant::Argon2id, 32, 0, 4).is_none());
+    assert!(Argon2::new(Variant::Argon2id, 32, 1, 0).is_none());
+    assert!(Argon2::new(Variant::Argon2id, 31, 1, 4).is_none());
+    assert!(Argon2::new(Variant::Argon2id, 1 << 30, 1, 1 << 24).is_none());
+
+    let argon2 = Argon2::new(Variant::Argon2id, 32, 1, 4).unwrap();
+    assert!(argon2.hash(b"password", b"short", &[], &[], 32).is_none());
+    assert!(argon2.hash(b"password", b"somesalt", &[], &[], 3).is_none());
+}
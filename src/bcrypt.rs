@@ -0,0 +1,145 @@
+use blowfish::Blowfish;
+use cipher::BlockCipher;
+use constanttime::ConstantTimeEq;
+use securemem::SecureMem;
+
+use std::cmp::min;
+use std::from_str::from_str;
+use std::slice::bytes;
+
+/// bcrypt's radix-64 alphabet, which is not the one base64 uses.
+static ALPHABET: &'static [u8] = b"./ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+pub const SALT_LEN: uint = 16;
+/// The raw hash length; bcrypt throws away the last byte of its output.
+pub const HASH_LEN: uint = 23;
+
+fn encode(data: &[u8]) -> String {
+    let mut out = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0u);
+    for &b in data.iter() {
+        acc = ((acc << 8) | b as u32) & 0xffff;
+        bits += 8;
+        while bits >= 6 {
+            bits -= 6;
+            out.push(ALPHABET[((acc >> bits) & 63) as uint]);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((acc << (6 - bits)) & 63) as uint]);
+    }
+    String::from_utf8(out).unwrap()
+}
+
+/// Decodes radix-64, ignoring the bits left over at the end.
+fn decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0u);
+    for c in s.bytes() {
+        let v = match ALPHABET.iter().position(|&a| a == c) {
+            Some(v) => v as u32,
+            None => return None,
+        };
+        acc = ((acc << 6) | v) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// The raw bcrypt hash of `password` with a 16-byte salt. Only the first
+/// 72 bytes of the password count. Returns None if the cost isn't between
+/// 4 and 31 or the salt has the wrong length.
+pub fn bcrypt(cost: u32, salt: &[u8], password: &[u8]) -> Option<Vec<u8>> {
+    if cost < 4 || cost > 31 || salt.len() != SALT_LEN {
+        return None;
+    }
+
+    // The key is the password with its NUL terminator, cut off at 72 bytes.
+    let len = min(password.len() + 1, 72);
+    let mut key = SecureMem::new(Vec::from_elem(len, 0u8));
+    let n = min(password.len(), len);
+    bytes::copy_memory(key.as_mut_slice(), password[..n]);
+
+    let bf = Blowfish::eks_setup(cost, salt, key.as_slice());
+    let mut ctext = b"OrpheanBeholderScryDoubt".to_vec();
+    for _ in range(0u, 64) {
+        ctext = bf.encrypt_ecb(ctext[]);
+    }
+    ctext.truncate(HASH_LEN);
+    Some(ctext)
+}
+
+/// Hashes `password` into a `$2b$` string.
+pub fn hash(password: &[u8], cost: u32, salt: &[u8]) -> Option<String> {
+    bcrypt(cost, salt, password).map(|h| {
+        format!("$2b${:02}${}{}", cost, encode(salt), encode(h[]))
+    })
+}
+
+/// Checks `password` against a `$2a$`, `$2b$` or `$2y$` string. All three
+/// are hashed the same way; they only differ in which historical bugs the
+/// writing implementation had for non-ASCII or very long passwords.
+pub fn verify(password: &[u8], hashed: &str) -> bool {
+    if hashed.len() != 60 || !hashed.bytes().all(|b| b < 0x80) {
+        return false;
+    }
+    let (prefix, rest) = (hashed[..7], hashed[7..]);
+    let version_ok = prefix.starts_with("$2a$") || prefix.starts_with("$2b$")
+        || prefix.starts_with("$2y$");
+    if !version_ok || !prefix.ends_with("$") {
+        return false;
+    }
+    let cost = match from_str::<u32>(prefix[4..6]) {
+        Some(cost) => cost,
+        None => return false,
+    };
+
+    let (salt, expected) = match (decode(rest[..22]), decode(rest[22..])) {
+        (Some(salt), Some(expected)) => (salt, expected),
+        _ => return false,
+    };
+    match bcrypt(cost, salt[], password) {
+        Some(h) => h[].const_time_eq(&expected[]),
+        None => false,
+    }
+}
+
+#[test]
+fn test_bcrypt_hash() {
+    let salt = b"sixteen byte slt";
+    assert_eq!(hash(b"", 4, salt).unwrap()[],
+               "$2b$04$a0j2bETjZg/gcVPjGFLqb.ynvCDT/aylSHFp35yv6e4pZeAA5m/Sm");
+    assert_eq!(hash(b"password", 5, salt).unwrap()[],
+               "$2b$05$a0j2bETjZg/gcVPjGFLqb.P4gIE/0Fl8GqfK2b.3pietrKzr2FvZe");
+    assert_eq!(hash(b"correct horse battery staple", 6, salt).unwrap()[],
+               "$2b$06$a0j2bETjZg/gcVPjGFLqb.3TUpoz8ZYjTIQrkvRTmXahPChHM8jDa");
+}
+
+#[test]
+fn test_bcrypt_verify() {
+    assert!(verify(b"U*U", "$2a$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW"));
+    assert!(!verify(b"U*V", "$2a$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW"));
+    assert!(verify(b"password", "$2b$05$a0j2bETjZg/gcVPjGFLqb.P4gIE/0Fl8GqfK2b.3pietrKzr2FvZe"));
+    assert!(verify(b"password", "$2y$05$a0j2bETjZg/gcVPjGFLqb.P4gIE/0Fl8GqfK2b.3pietrKzr2FvZe"));
+
+    // Only the first 72 bytes count.
+    let long = Vec::from_elem(100, b'a');
+    assert!(verify(long[], "$2y$04$a0j2bETjZg/gcVPjGFLqb.D9eU2fu6PdYOWaEP8.8P7AUdaSfOH26"));
+    assert!(verify(long[..72], "$2y$04$a0j2bETjZg/gcVPjGFLqb.D9eU2fu6PdYOWaEP8.8P7AUdaSfOH26"));
+
+    assert!(!verify(b"password", "$2x$05$a0j2bETjZg/gcVPjGFLqb.P4gIE/0Fl8GqfK2b.3pietrKzr2FvZe"));
+    assert!(!verify(b"password", "$2b$03$a0j2bETjZg/gcVPjGFLqb.P4gIE/0Fl8GqfK2b.3pietrKzr2FvZe"));
+    assert!(!verify(b"password", "$2b$05$a0j2bETjZg/gcVPjGFLqb.P4gIE/0Fl8GqfK2b.3pietrKzr2FvZ"));
+    assert!(!verify(b"password", ""));
+}
+
+#[test]
+fn test_bcrypt_bad_params() {
+    assert!(bcrypt(3, b"sixteen byte slt", b"password").is_none());
+    assert!(bcrypt(32, b"sixteen byte slt", b"password").is_none());
+    assert!(bcrypt(4, b"short salt", b"password").is_none());
+}
@@ -0,0 +1,369 @@
+use HashFn;
+
+use std::cmp::min;
+use std::slice::bytes;
+
+static SIGMA: [[uint, ..16], ..10] = [
+    [ 0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15],
+    [14, 10,  4,  8,  9, 15, 13,  6,  1, 12,  0,  2, 11,  7,  5,  3],
+    [11,  8, 12,  0,  5,  2, 15, 13, 10, 14,  3,  6,  7,  1,  9,  4],
+    [ 7,  9,  3,  1, 13, 12, 11, 14,  2,  6,  5, 10,  4,  0, 15,  8],
+    [ 9,  0,  5,  7,  2,  4, 10, 15, 14,  1, 11, 12,  6,  8,  3, 13],
+    [ 2, 12,  6, 10,  0, 11,  8,  3,  4, 13,  7,  5, 15, 14,  1,  9],
+    [12,  5,  1, 15, 14, 13,  4, 10,  0,  7,  6,  3,  9,  2,  8, 11],
+    [13, 11,  7, 14, 12,  1,  3,  9,  5,  0, 15,  4,  8,  6,  2, 10],
+    [ 6, 15, 14,  9, 11,  3,  0,  8, 12,  2, 13,  7,  1,  4, 10,  5],
+    [10,  2,  8,  4,  7,  6,  1,  5, 15, 11,  9, 14,  3, 12, 13,  0],
+        ];
+
+static BLAKE2B_IV: [u64, ..8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+        ];
+
+static BLAKE2S_IV: [u32, ..8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+        ];
+
+/// The parts of the BLAKE2 parameter block that matter for sequential
+/// hashing. Empty slices leave the corresponding parameter unset; a salt or
+/// personalization shorter than the maximum is padded with zeroes.
+pub struct Blake2Params<'a> {
+    pub digest_len: uint,
+    pub key: &'a [u8],
+    pub salt: &'a [u8],
+    pub personal: &'a [u8],
+}
+
+impl<'a> Blake2Params<'a> {
+    pub fn new(digest_len: uint) -> Blake2Params<'a> {
+        Blake2Params {
+            digest_len: digest_len,
+            key: &[],
+            salt: &[],
+            personal: &[],
+        }
+    }
+}
+
+#[inline(always)]
+fn load_b(data: &[u8], i: uint) -> u64 { get_64l!(data, i) }
+#[inline(always)]
+fn load_s(data: &[u8], i: uint) -> u32 { unpack!(data, i) }
+#[inline(always)]
+fn store_b(w: u64, out: &mut [u8]) { store_64l!(w, out) }
+#[inline(always)]
+fn store_s(w: u32, out: &mut [u8]) { store_32l!(w, out) }
+
+macro_rules! blake2_impl (
+    ($name: ident, $word: ty, $block: expr, $outmax: expr, $saltlen: expr,
+     $rounds: expr, $iv: ident, $load: ident, $store: ident,
+     $r1: expr, $r2: expr, $r3: expr, $r4: expr) => (
+        pub struct $name {
+            h: [$word, ..8],
+            t: [$word, ..2],
+            buf: [u8, ..$block],
+            buflen: uint,
+            outlen: uint,
+        }
+
+        impl Clone for $name {
+            fn clone(&self) -> $name {
+                $name {
+                    h: self.h,
+                    t: self.t,
+                    buf: self.buf,
+                    buflen: self.buflen,
+                    outlen: self.outlen,
+                }
+            }
+        }
+
+        impl $name {
+            /// Unkeyed hashing with a `digest_len`-byte output.
+            pub fn new(digest_len: uint) -> Option<$name> {
+                $name::with_params(&Blake2Params::new(digest_len))
+            }
+
+            /// Keyed hashing, i.e. BLAKE2 used directly as a MAC.
+            pub fn new_keyed(key: &[u8], digest_len: uint) -> Option<$name> {
+                let mut params = Blake2Params::new(digest_len);
+                params.key = key;
+                $name::with_params(&params)
+            }
+
+            /// Returns None if any parameter is out of range for this variant.
+            pub fn with_params(params: &Blake2Params) -> Option<$name> {
+                if params.digest_len == 0 || params.digest_len > $outmax
+                    || params.key.len() > $outmax
+                    || params.salt.len() > $saltlen
+                    || params.personal.len() > $saltlen {
+                    return None;
+                }
+
+                let mut h = $iv;
+                h[0] ^= (params.digest_len | (params.key.len() << 8)
+                         | (1 << 16) | (1 << 24)) as $word;
+
+                let mut salt = [0u8, ..$saltlen];
+                bytes::copy_memory(salt[mut], params.salt);
+                h[4] ^= $load(salt[], 0);
+                h[5] ^= $load(salt[], 1);
+
+                let mut personal = [0u8, ..$saltlen];
+                bytes::copy_memory(personal[mut], params.personal);
+                h[6] ^= $load(personal[], 0);
+                h[7] ^= $load(personal[], 1);
+
+                let mut state = $name {
+                    h: h,
+                    t: [0, 0],
+                    buf: [0, ..$block],
+                    buflen: 0,
+                    outlen: params.digest_len,
+                };
+
+                // The key is processed as a zero-padded first block.
+                if params.key.len() > 0 {
+                    bytes::copy_memory(state.buf[mut], params.key);
+                    state.buflen = $block;
+                }
+
+                Some(state)
+            }
+
+            fn increment_counter(&mut self, n: uint) {
+                let n = n as $word;
+                self.t[0] += n;
+                if self.t[0] < n {
+                    self.t[1] += 1;
+                }
+            }
+
+            fn compress(h: &mut [$word, ..8], t: [$word, ..2], block: &[u8], last: bool) {
+                let mut m = [0 as $word, ..16];
+                for i in range(0u, 16) {
+                    m[i] = $load(block, i);
+                }
+
+                let mut v = [0 as $word, ..16];
+                for i in range(0u, 8) {
+                    v[i] = h[i];
+                    v[i + 8] = $iv[i];
+                }
+                v[12] ^= t[0];
+                v[13] ^= t[1];
+                if last {
+                    v[14] = !v[14];
+                }
+
+                for r in range(0u, $rounds) {
+                    let s = &SIGMA[r % 10];
+                    blake2_g!(v, 0, 4,  8, 12, m[s[ 0]], m[s[ 1]], $r1, $r2, $r3, $r4);
+                    blake2_g!(v, 1, 5,  9, 13, m[s[ 2]], m[s[ 3]], $r1, $r2, $r3, $r4);
+                    blake2_g!(v, 2, 6, 10, 14, m[s[ 4]], m[s[ 5]], $r1, $r2, $r3, $r4);
+                    blake2_g!(v, 3, 7, 11, 15, m[s[ 6]], m[s[ 7]], $r1, $r2, $r3, $r4);
+                    blake2_g!(v, 0, 5, 10, 15, m[s[ 8]], m[s[ 9]], $r1, $r2, $r3, $r4);
+                    blake2_g!(v, 1, 6, 11, 12, m[s[10]], m[s[11]], $r1, $r2, $r3, $r4);
+                    blake2_g!(v, 2, 7,  8, 13, m[s[12]], m[s[13]], $r1, $r2, $r3, $r4);
+                    blake2_g!(v, 3, 4,  9, 14, m[s[14]], m[s[15]], $r1, $r2, $r3, $r4);
+                }
+
+                for i in range(0u, 8) {
+                    h[i] ^= v[i] ^ v[i + 8];
+                }
+            }
+        }
+
+        impl HashFn for $name {
+            fn create() -> $name {
+                $name::new($outmax).unwrap()
+            }
+
+            fn update(&mut self, data: &[u8]) {
+                let mut data = data;
+                while data.len() > 0 {
+                    // The final block has to be compressed differently, so
+                    // a full buffer is only flushed once more input shows up.
+                    if self.buflen == $block {
+                        self.increment_counter($block);
+                        $name::compress(&mut self.h, self.t, &self.buf, false);
+                        self.buflen = 0;
+                    }
+                    let n = min(data.len(), $block - self.buflen);
+                    bytes::copy_memory(self.buf[mut self.buflen..self.buflen + n], data[..n]);
+                    self.buflen += n;
+                    data = data[n..];
+                }
+            }
+
+            fn digest(mut self) -> Vec<u8> {
+                let buflen = self.buflen;
+                self.increment_counter(buflen);
+                for i in range(buflen, $block) {
+                    self.buf[i] = 0;
+                }
+                $name::compress(&mut self.h, self.t, &self.buf, true);
+
+                let wordlen = $block / 16;
+                let mut out = Vec::from_elem(8 * wordlen, 0u8);
+                for i in range(0u, 8) {
+                    $store(self.h[i], out[mut i*wordlen..]);
+                }
+                out.truncate(self.outlen);
+
+                return out;
+            }
+
+            fn block_size(_: Option<&$name>) -> uint { $block }
+
+            fn output_size(_: Option<&$name>) -> uint { $outmax }
+        }
+    )
+)
+
+blake2_impl!(Blake2b, u64, 128, 64, 16, 12, BLAKE2B_IV, load_b, store_b, 32, 24, 16, 63)
+blake2_impl!(Blake2s, u32, 64, 32, 8, 10, BLAKE2S_IV, load_s, store_s, 16, 12, 8, 7)
+
+#[test]
+fn test_blake2_rfc7693_abc() {
+    use serialize::hex::ToHex;
+    let mut b: Blake2b = HashFn::create();
+    b.update(b"abc");
+    assert_eq!(b.digest()[].to_hex()[],
+               "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923");
+
+    let mut s: Blake2s = HashFn::create();
+    s.update(b"abc");
+    assert_eq!(s.digest()[].to_hex()[],
+               "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982");
+}
+
+#[cfg(test)]
+fn selftest_seq(len: uint, seed: u32) -> Vec<u8> {
+    let mut a = 0xdead4bad * seed;
+    let mut b = 1u32;
+    let mut out = Vec::with_capacity(len);
+    for _ in range(0, len) {
+        let t = a + b;
+        a = b;
+        b = t;
+        out.push((t >> 24) as u8);
+    }
+    out
+}
+
+#[test]
+fn test_blake2b_rfc7693_selftest() {
+    use serialize::hex::ToHex;
+    static MD_LEN: [uint, ..4] = [20, 32, 48, 64];
+    static IN_LEN: [uint, ..6] = [0, 3, 128, 129, 255, 1024];
+
+    let mut ctx = Blake2b::new(32).unwrap();
+    for &outlen in MD_LEN.iter() {
+        for &inlen in IN_LEN.iter() {
+            let input = selftest_seq(inlen, inlen as u32);
+
+            let mut h = Blake2b::new(outlen).unwrap();
+            h.update(input[]);
+            ctx.update(h.digest()[]);
+
+            let key = selftest_seq(outlen, outlen as u32);
+            let mut h = Blake2b::new_keyed(key[], outlen).unwrap();
+            h.update(input[]);
+            ctx.update(h.digest()[]);
+        }
+    }
+    assert_eq!(ctx.digest()[].to_hex()[],
+               "c23a7800d98123bd10f506c61e29da5603d763b8bbad2e737f5e765a7bccd475");
+}
+
+#[test]
+fn test_blake2s_rfc7693_selftest() {
+    use serialize::hex::ToHex;
+    static MD_LEN: [uint, ..4] = [16, 20, 28, 32];
+    static IN_LEN: [uint, ..6] = [0, 3, 64, 65, 255, 1024];
+
+    let mut ctx = Blake2s::new(32).unwrap();
+    for &outlen in MD_LEN.iter() {
+        for &inlen in IN_LEN.iter() {
+            let input = selftest_seq(inlen, inlen as u32);
+
+            let mut h = Blake2s::new(outlen).unwrap();
+            h.update(input[]);
+            ctx.update(h.digest()[]);
+
+            let key = selftest_seq(outlen, outlen as u32);
+            let mut h = Blake2s::new_keyed(key[], outlen).unwrap();
+            h.update(input[]);
+            ctx.update(h.digest()[]);
+        }
+    }
+    assert_eq!(ctx.digest()[].to_hex()[],
+               "6a411f08ce25adcdfb02aba641451cec53c598b24f4fc787fbdc88797f4c1dfe");
+}
+
+#[test]
+fn test_blake2_keyed_kat() {
+    use serialize::hex::ToHex;
+    // Entries from blake2b-kat.txt and blake2s-kat.txt: the key is
+    // 00 01 02 ..., and so is the input.
+    static VECTORS_B: &'static [(uint, &'static str)] = &[
+        (0, "10ebb67700b1868efb4417987acf4690ae9d972fb7a590c2f02871799aaa4786b5e996e8f0f4eb981fc214b005f42d2ff4233499391653df7aefcbc13fc51568"),
+        (1, "961f6dd1e4dd30f63901690c512e78e4b45e4742ed197c3c5e45c549fd25f2e4187b0bc9fe30492b16b0d0bc4ef9b0f34c7003fac09a5ef1532e69430234cebd"),
+        (255, "142709d62e28fcccd0af97fad0f8465b971e82201dc51070faa0372aa43e92484be1c1e73ba10906d5d1853db6a4106e0a7bf9800d373d6dee2d46d62ef2a461"),
+    ];
+    static VECTORS_S: &'static [(uint, &'static str)] = &[
+        (0, "48a8997da407876b3d79c0d92325ad3b89cbb754d86ab71aee047ad345fd2c49"),
+        (1, "40d15fee7c328830166ac3f918650f807e7e01e177258cdc0a39b11f598066f1"),
+        (255, "3fb735061abc519dfe979e54c1ee5bfad0a9d858b3315bad34bde999efd724dd"),
+    ];
+
+    let input: Vec<u8> = range(0u, 256).map(|i| i as u8).collect();
+
+    for &(len, md) in VECTORS_B.iter() {
+        let mut h = Blake2b::new_keyed(input[..64], 64).unwrap();
+        h.update(input[..len]);
+        assert_eq!(h.digest()[].to_hex()[], md);
+    }
+
+    for &(len, md) in VECTORS_S.iter() {
+        let mut h = Blake2s::new_keyed(input[..32], 32).unwrap();
+        h.update(input[..len]);
+        assert_eq!(h.digest()[].to_hex()[], md);
+    }
+}
+
+#[test]
+fn test_blake2_salt_personal() {
+    use serialize::hex::ToHex;
+    let params = Blake2Params {
+        digest_len: 32,
+        key: b"secret key",
+        salt: b"0123456789abcdef",
+        personal: b"crusty-crypto-v1",
+    };
+    let mut h = Blake2b::with_params(&params).unwrap();
+    h.update(b"hello");
+    assert_eq!(h.digest()[].to_hex()[],
+               "2c44534cfb4b0d36304bab79d723da1cba8621e913e472162927e56cf5416d14");
+
+    let params = Blake2Params {
+        digest_len: 16,
+        key: b"secret key",
+        salt: b"01234567",
+        personal: b"crusty-1",
+    };
+    let mut h = Blake2s::with_params(&params).unwrap();
+    h.update(b"hello");
+    assert_eq!(h.digest()[].to_hex()[], "d106fd1a9d2947d5e7c4956b61234a1f");
+}
+
+#[test]
+fn test_blake2_bad_params() {
+    assert!(Blake2b::new(0).is_none());
+    assert!(Blake2b::new(65).is_none());
+    assert!(Blake2s::new(33).is_none());
+    assert!(Blake2s::new_keyed(Vec::from_elem(33, 0u8)[], 32).is_none());
+}
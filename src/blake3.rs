@@ -0,0 +1,552 @@
+use {HashFn, XofReader};
+
+use std::cmp::min;
+use std::slice::bytes;
+use std::sync::Arc;
+
+pub const KEY_LEN: uint = 32;
+pub const OUT_LEN: uint = 32;
+const BLOCK_LEN: uint = 64;
+const CHUNK_LEN: uint = 1024;
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+const KEYED_HASH: u32 = 1 << 4;
+const DERIVE_KEY_CONTEXT: u32 = 1 << 5;
+const DERIVE_KEY_MATERIAL: u32 = 1 << 6;
+
+static IV: [u32, ..8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+        ];
+
+static MSG_PERMUTATION: [uint, ..16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+fn round(state: &mut [u32, ..16], m: &[u32, ..16]) {
+    blake2_g!(state, 0, 4,  8, 12, m[ 0], m[ 1], 16, 12, 8, 7);
+    blake2_g!(state, 1, 5,  9, 13, m[ 2], m[ 3], 16, 12, 8, 7);
+    blake2_g!(state, 2, 6, 10, 14, m[ 4], m[ 5], 16, 12, 8, 7);
+    blake2_g!(state, 3, 7, 11, 15, m[ 6], m[ 7], 16, 12, 8, 7);
+    blake2_g!(state, 0, 5, 10, 15, m[ 8], m[ 9], 16, 12, 8, 7);
+    blake2_g!(state, 1, 6, 11, 12, m[10], m[11], 16, 12, 8, 7);
+    blake2_g!(state, 2, 7,  8, 13, m[12], m[13], 16, 12, 8, 7);
+    blake2_g!(state, 3, 4,  9, 14, m[14], m[15], 16, 12, 8, 7);
+}
+
+fn compress(cv: &[u32, ..8], block_words: &[u32, ..16], counter: u64,
+            block_len: u32, flags: u32) -> [u32, ..16] {
+    let mut state = [
+        cv[0], cv[1], cv[2], cv[3], cv[4], cv[5], cv[6], cv[7],
+        IV[0], IV[1], IV[2], IV[3],
+        counter as u32, (counter >> 32) as u32, block_len, flags,
+        ];
+    let mut m = *block_words;
+
+    for r in range(0u, 7) {
+        round(&mut state, &m);
+        if r < 6 {
+            let mut permuted = [0u32, ..16];
+            for i in range(0u, 16) {
+                permuted[i] = m[MSG_PERMUTATION[i]];
+            }
+            m = permuted;
+        }
+    }
+
+    for i in range(0u, 8) {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= cv[i];
+    }
+
+    return state;
+}
+
+fn first_8_words(words: [u32, ..16]) -> [u32, ..8] {
+    let mut out = [0u32, ..8];
+    for i in range(0u, 8) {
+        out[i] = words[i];
+    }
+    out
+}
+
+fn block_words(block: &[u8]) -> [u32, ..16] {
+    let mut words = [0u32, ..16];
+    for i in range(0u, 16) {
+        words[i] = unpack!(block, i);
+    }
+    words
+}
+
+fn key_words(key: &[u8]) -> [u32, ..8] {
+    let mut words = [0u32, ..8];
+    for i in range(0u, 8) {
+        words[i] = unpack!(key, i);
+    }
+    words
+}
+
+/// Everything needed to produce either a node's chaining value or, for the
+/// root node, any number of output bytes.
+#[deriving(Clone)]
+struct Output {
+    input_cv: [u32, ..8],
+    block_words: [u32, ..16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn chaining_value(&self) -> [u32, ..8] {
+        first_8_words(compress(&self.input_cv, &self.block_words, self.counter,
+                               self.block_len, self.flags))
+    }
+
+    fn root_output_block(&self, block_counter: u64, out: &mut [u8]) {
+        let words = compress(&self.input_cv, &self.block_words, block_counter,
+                             self.block_len, self.flags | ROOT);
+        for i in range(0u, 16) {
+            store_32l!(words[i], out[mut 4*i..]);
+        }
+    }
+}
+
+struct ChunkState {
+    cv: [u32, ..8],
+    chunk_counter: u64,
+    block: [u8, ..BLOCK_LEN],
+    block_len: uint,
+    blocks_compressed: uint,
+    flags: u32,
+}
+
+impl Clone for ChunkState {
+    fn clone(&self) -> ChunkState {
+        ChunkState {
+            cv: self.cv,
+            chunk_counter: self.chunk_counter,
+            block: self.block,
+            block_len: self.block_len,
+            blocks_compressed: self.blocks_compressed,
+            flags: self.flags,
+        }
+    }
+}
+
+impl ChunkState {
+    fn new(key_words: &[u32, ..8], chunk_counter: u64, flags: u32) -> ChunkState {
+        ChunkState {
+            cv: *key_words,
+            chunk_counter: chunk_counter,
+            block: [0, ..BLOCK_LEN],
+            block_len: 0,
+            blocks_compressed: 0,
+            flags: flags,
+        }
+    }
+
+    fn len(&self) -> uint {
+        BLOCK_LEN * self.blocks_compressed + self.block_len
+    }
+
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 { CHUNK_START } else { 0 }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        let mut input = input;
+        while input.len() > 0 {
+            // As in BLAKE2, the last block of a chunk is compressed
+            // differently, so a full block waits until more input arrives.
+            if self.block_len == BLOCK_LEN {
+                let words = block_words(&self.block);
+                let flags = self.flags | self.start_flag();
+                self.cv = first_8_words(compress(&self.cv, &words, self.chunk_counter,
+                                                 BLOCK_LEN as u32, flags));
+                self.blocks_compressed += 1;
+                self.block = [0, ..BLOCK_LEN];
+                self.block_len = 0;
+            }
+
+            let take = min(BLOCK_LEN - self.block_len, input.len());
+            bytes::copy_memory(self.block[mut self.block_len..], input[..take]);
+            self.block_len += take;
+            input = input[take..];
+        }
+    }
+
+    fn output(&self) -> Output {
+        Output {
+            input_cv: self.cv,
+            block_words: block_words(&self.block),
+            counter: self.chunk_counter,
+            block_len: self.block_len as u32,
+            flags: self.flags | self.start_flag() | CHUNK_END,
+        }
+    }
+}
+
+fn parent_output(left_cv: &[u32, ..8], right_cv: &[u32, ..8],
+                 key_words: &[u32, ..8], flags: u32) -> Output {
+    let mut words = [0u32, ..16];
+    for i in range(0u, 8) {
+        words[i] = left_cv[i];
+        words[i + 8] = right_cv[i];
+    }
+    Output {
+        input_cv: *key_words,
+        block_words: words,
+        counter: 0,
+        block_len: BLOCK_LEN as u32,
+        flags: PARENT | flags,
+    }
+}
+
+fn parent_cv(left_cv: &[u32, ..8], right_cv: &[u32, ..8],
+             key_words: &[u32, ..8], flags: u32) -> [u32, ..8] {
+    parent_output(left_cv, right_cv, key_words, flags).chaining_value()
+}
+
+/// The number of bytes that go in the left subtree of a node covering
+/// `len` bytes: the largest power-of-two number of whole chunks that still
+/// leaves something for the right subtree.
+fn left_len(len: uint) -> uint {
+    assert!(len > CHUNK_LEN);
+    let full_chunks = (len - 1) / CHUNK_LEN;
+    let mut chunks = 1;
+    while chunks * 2 <= full_chunks {
+        chunks *= 2;
+    }
+    chunks * CHUNK_LEN
+}
+
+fn subtree_cv(input: &[u8], key_words: &[u32, ..8], chunk_counter: u64,
+              flags: u32) -> [u32, ..8] {
+    if input.len() <= CHUNK_LEN {
+        let mut chunk = ChunkState::new(key_words, chunk_counter, flags);
+        chunk.update(input);
+        return chunk.output().chaining_value();
+    }
+
+    let split = left_len(input.len());
+    let left = subtree_cv(input[..split], key_words, chunk_counter, flags);
+    let right = subtree_cv(input[split..], key_words,
+                           chunk_counter + (split / CHUNK_LEN) as u64, flags);
+    parent_cv(&left, &right, key_words, flags)
+}
+
+/// Computes the chaining values of both children of the node covering
+/// `input[start..end]`, handing the right child to another task while this
+/// one does the left.
+fn children_parallel(input: Arc<Vec<u8>>, start: uint, end: uint, key_words: [u32, ..8],
+                     chunk_counter: u64, flags: u32,
+                     threads: uint) -> ([u32, ..8], [u32, ..8]) {
+    let split = start + left_len(end - start);
+    let right_counter = chunk_counter + ((split - start) / CHUNK_LEN) as u64;
+    let right_threads = threads / 2;
+
+    let (tx, rx) = channel();
+    let right_input = input.clone();
+    spawn(proc() {
+        tx.send(subtree_cv_parallel(right_input, split, end, key_words,
+                                    right_counter, flags, right_threads));
+    });
+
+    let left = subtree_cv_parallel(input, start, split, key_words,
+                                   chunk_counter, flags, threads - right_threads);
+    (left, rx.recv())
+}
+
+fn subtree_cv_parallel(input: Arc<Vec<u8>>, start: uint, end: uint, key_words: [u32, ..8],
+                       chunk_counter: u64, flags: u32, threads: uint) -> [u32, ..8] {
+    if threads <= 1 || end - start <= CHUNK_LEN {
+        return subtree_cv(input.as_slice()[start..end], &key_words, chunk_counter, flags);
+    }
+
+    let (left, right) = children_parallel(input, start, end, key_words,
+                                          chunk_counter, flags, threads);
+    parent_cv(&left, &right, &key_words, flags)
+}
+
+#[deriving(Clone)]
+pub struct Blake3 {
+    chunk_state: ChunkState,
+    key_words: [u32, ..8],
+    cv_stack: Vec<[u32, ..8]>,
+    flags: u32,
+}
+
+impl Blake3 {
+    fn new_internal(key_words: &[u32, ..8], flags: u32) -> Blake3 {
+        Blake3 {
+            chunk_state: ChunkState::new(key_words, 0, flags),
+            key_words: *key_words,
+            cv_stack: Vec::new(),
+            flags: flags,
+        }
+    }
+
+    /// Keyed hashing, which makes BLAKE3 a MAC. The key must be 32 bytes.
+    pub fn new_keyed(key: &[u8]) -> Option<Blake3> {
+        if key.len() == KEY_LEN {
+            Some(Blake3::new_internal(&key_words(key), KEYED_HASH))
+        } else { None }
+    }
+
+    /// Key derivation: `context` should be a hardcoded, globally unique
+    /// string describing the purpose of the key, and the key material is
+    /// then supplied through `update`.
+    pub fn new_derive_key(context: &str) -> Blake3 {
+        let mut context_hasher = Blake3::new_internal(&IV, DERIVE_KEY_CONTEXT);
+        context_hasher.update(context.as_bytes());
+        let context_key = context_hasher.digest();
+        Blake3::new_internal(&key_words(context_key[]), DERIVE_KEY_MATERIAL)
+    }
+
+    fn add_chunk_chaining_value(&mut self, cv: [u32, ..8], total_chunks: u64) {
+        // Every completed subtree is merged as soon as it's known to be
+        // complete, which is exactly when the chunk count has a trailing
+        // zero bit for it.
+        let mut cv = cv;
+        let mut total_chunks = total_chunks;
+        while total_chunks & 1 == 0 {
+            let left = self.cv_stack.pop().unwrap();
+            cv = parent_cv(&left, &cv, &self.key_words, self.flags);
+            total_chunks >>= 1;
+        }
+        self.cv_stack.push(cv);
+    }
+
+    fn final_output(&self) -> Output {
+        let mut output = self.chunk_state.output();
+        for left in self.cv_stack.iter().rev() {
+            output = parent_output(left, &output.chaining_value(), &self.key_words, self.flags);
+        }
+        output
+    }
+
+    /// Finishes hashing and returns a reader over the unbounded output
+    /// stream, whose first 32 bytes are the same as `digest`.
+    pub fn finalize_xof(self) -> Blake3Reader {
+        Blake3Reader {
+            output: self.final_output(),
+            block_counter: 0,
+            block: [0, ..2 * OUT_LEN],
+            pos: 2 * OUT_LEN,
+        }
+    }
+
+    /// Hashes all of `input` in this hasher's mode (plain, keyed or key
+    /// derivation), splitting the chunk tree across up to `threads` tasks.
+    /// The result is the same as a fresh hasher of the same kind given
+    /// `input` through `update`; anything already given to this one plays no
+    /// part. The tasks share `input`, hence the `Arc`.
+    pub fn hash_parallel(&self, input: Arc<Vec<u8>>, threads: uint) -> Vec<u8> {
+        let len = input.len();
+        if threads <= 1 || len <= CHUNK_LEN {
+            let mut hasher = Blake3::new_internal(&self.key_words, self.flags);
+            hasher.update(input.as_slice());
+            return hasher.digest();
+        }
+
+        let (left, right) = children_parallel(input, 0, len, self.key_words, 0,
+                                              self.flags, threads);
+        let mut out = Vec::from_elem(2 * OUT_LEN, 0u8);
+        parent_output(&left, &right, &self.key_words, self.flags)
+            .root_output_block(0, out[mut]);
+        out.truncate(OUT_LEN);
+        out
+    }
+}
+
+impl HashFn for Blake3 {
+    fn create() -> Blake3 {
+        Blake3::new_internal(&IV, 0)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        let mut input = data;
+        while input.len() > 0 {
+            if self.chunk_state.len() == CHUNK_LEN {
+                let chunk_cv = self.chunk_state.output().chaining_value();
+                let total_chunks = self.chunk_state.chunk_counter + 1;
+                self.add_chunk_chaining_value(chunk_cv, total_chunks);
+                self.chunk_state = ChunkState::new(&self.key_words, total_chunks, self.flags);
+            }
+
+            let take = min(CHUNK_LEN - self.chunk_state.len(), input.len());
+            self.chunk_state.update(input[..take]);
+            input = input[take..];
+        }
+    }
+
+    fn digest(self) -> Vec<u8> {
+        let mut out = Vec::from_elem(OUT_LEN, 0u8);
+        self.finalize_xof().read(out[mut]);
+        out
+    }
+
+    fn block_size(_: Option<&Blake3>) -> uint { BLOCK_LEN }
+
+    fn output_size(_: Option<&Blake3>) -> uint { OUT_LEN }
+}
+
+pub struct Blake3Reader {
+    output: Output,
+    block_counter: u64,
+    block: [u8, ..2 * OUT_LEN],
+    pos: uint,
+}
+
+impl XofReader for Blake3Reader {
+    fn read(&mut self, out: &mut [u8]) {
+        for o in out.iter_mut() {
+            if self.pos == self.block.len() {
+                self.output.root_output_block(self.block_counter, self.block[mut]);
+                self.block_counter += 1;
+                self.pos = 0;
+            }
+            *o = self.block[self.pos];
+            self.pos += 1;
+        }
+    }
+}
+
+/// One-shot key derivation, filling all of `out`.
+pub fn derive_key(context: &str, key_material: &[u8], out: &mut [u8]) {
+    let mut hasher = Blake3::new_derive_key(context);
+    hasher.update(key_material);
+    hasher.finalize_xof().read(out);
+}
+
+#[cfg(test)]
+fn test_input(len: uint) -> Vec<u8> {
+    range(0, len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn test_blake3_vectors() {
+    use serialize::hex::ToHex;
+    // From the official test_vectors.json: (input_len, hash, keyed_hash,
+    // derive_key), truncated to the default 32-byte output.
+    static KEY: &'static [u8] = b"whats the Elvish word for friend";
+    static CONTEXT: &'static str = "BLAKE3 2019-12-27 16:29:52 test vectors context";
+    static VECTORS: &'static [(uint, &'static str, &'static str, &'static str)] = &[
+        (0, "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+         "92b2b75604ed3c761f9d6f62392c8a9227ad0ea3f09573e783f1498a4ed60d26",
+         "2cc39783c223154fea8dfb7c1b1660f2ac2dcbd1c1de8277b0b0dd39b7e50d7d"),
+        (1, "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213",
+         "6d7878dfff2f485635d39013278ae14f1454b8c0a3a2d34bc1ab38228a80c95b",
+         "b3e2e340a117a499c6cf2398a19ee0d29cca2bb7404c73063382693bf66cb06c"),
+        (1023, "10108970eeda3eb932baac1428c7a2163b0e924c9a9e25b35bba72b28f70bd11",
+         "c951ecdf03288d0fcc96ee3413563d8a6d3589547f2c2fb36d9786470f1b9d6e",
+         "74a16c1c3d44368a86e1ca6df64be6a2f64cce8f09220787450722d85725dea5"),
+        (1024, "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7",
+         "75c46f6f3d9eb4f55ecaaee480db732e6c2105546f1e675003687c31719c7ba4",
+         "7356cd7720d5b66b6d0697eb3177d9f8d73a4a5c5e968896eb6a689684302706"),
+        (1025, "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444",
+         "357dc55de0c7e382c900fd6e320acc04146be01db6a8ce7210b7189bd664ea69",
+         "effaa245f065fbf82ac186839a249707c3bddf6d3fdda22d1b95a3c970379bcb"),
+        (2048, "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a",
+         "879cf1fa2ea0e79126cb1063617a05b6ad9d0b696d0d757cf053439f60a99dd1",
+         "7b2945cb4fef70885cc5d78a87bf6f6207dd901ff239201351ffac04e1088a23"),
+        (2049, "5f4d72f40d7a5f82b15ca2b2e44b1de3c2ef86c426c95c1af0b6879522563030",
+         "9f29700902f7c86e514ddc4df1e3049f258b2472b6dd5267f61bf13983b78dd5",
+         "2ea477c5515cc3dd606512ee72bb3e0e758cfae7232826f35fb98ca1bcbdf273"),
+        (3072, "b98cb0ff3623be03326b373de6b9095218513e64f1ee2edd2525c7ad1e5cffd2",
+         "044a0e7b172a312dc02a4c9a818c036ffa2776368d7f528268d2e6b5df191770",
+         "050df97f8c2ead654d9bb3ab8c9178edcd902a32f8495949feadcc1e0480c46b"),
+        (3073, "7124b49501012f81cc7f11ca069ec9226cecb8a2c850cfe644e327d22d3e1cd3",
+         "68dede9bef00ba89e43f31a6825f4cf433389fedae75c04ee9f0cf16a427c95a",
+         "72613c9ec9ff7e40f8f5c173784c532ad852e827dba2bf85b2ab4b76f7079081"),
+        (4096, "015094013f57a5277b59d8475c0501042c0b642e531b0a1c8f58d2163229e969",
+         "befc660aea2f1718884cd8deb9902811d332f4fc4a38cf7c7300d597a081bfc0",
+         "1e0d7f3db8c414c97c6307cbda6cd27ac3b030949da8e23be1a1a924ad2f25b9"),
+        (4097, "9b4052b38f1c5fc8b1f9ff7ac7b27cd242487b3d890d15c96a1c25b8aa0fb995",
+         "00df940cd36bb9fa7cbbc3556744e0dbc8191401afe70520ba292ee3ca80abbc",
+         "aca51029626b55fda7117b42a7c211f8c6e9ba4fe5b7a8ca922f34299500ead8"),
+        (8193, "bab6c09cb8ce8cf459261398d2e7aef35700bf488116ceb94a36d0f5f1b7bc3b",
+         "954a2a75420c8d6547e3ba5b98d963e6fa6491addc8c023189cc519821b4a1f5",
+         "af1e0346e389b17c23200270a64aa4e1ead98c61695d917de7d5b00491c9b0f1"),
+        (31744, "62b6960e1a44bcc1eb1a611a8d6235b6b4b78f32e7abc4fb4c6cdcce94895c47",
+         "efa53b389ab67c593dba624d898d0f7353ab99e4ac9d42302ee64cbf9939a419",
+         "39772aef80e0ebe60596361e45b061e8f417429d529171b6764468c22928e28e"),
+        (102400, "bc3e3d41a1146b069abffad3c0d44860cf664390afce4d9661f7902e7943e085",
+         "1c35d1a5811083fd7119f5d5d1ba027b4d01c0c6c49fb6ff2cf75393ea5db4a7",
+         "4652cff7a3f385a6103b5c260fc1593e13c778dbe608efb092fe7ee69df6e9c6"),
+    ];
+
+    for &(len, hash, keyed, derived) in VECTORS.iter() {
+        let input = test_input(len);
+
+        let mut h: Blake3 = HashFn::create();
+        h.update(input[]);
+        assert_eq!(h.digest()[].to_hex()[], hash);
+
+        let mut h = Blake3::new_keyed(KEY).unwrap();
+        h.update(input[]);
+        assert_eq!(h.digest()[].to_hex()[], keyed);
+
+        let mut out = [0u8, ..32];
+        derive_key(CONTEXT, input[], out[mut]);
+        assert_eq!(out[].to_hex()[], derived);
+    }
+}
+
+#[test]
+fn test_blake3_xof() {
+    use serialize::hex::ToHex;
+    static EXTENDED: &'static str = "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444f4c4a22b4b399155358a994e52bf255de60035742ec71bd08ac275a1b51cc6bfe332b0ef84b409108cda080e6269ed4b3e2c3f7d722aa4cdc98d16deb554e5627be8f955c98e1d5f9565a9194cad0c4285f93700062d9595adb992ae68ff12800ab67a";
+    let input = test_input(1025);
+
+    let mut h: Blake3 = HashFn::create();
+    h.update(input[]);
+    let mut reader = h.finalize_xof();
+    let mut out = Vec::from_elem(131, 0u8);
+    reader.read(out[mut ..7]);
+    reader.read(out[mut 7..100]);
+    reader.read(out[mut 100..]);
+    assert_eq!(out[].to_hex()[], EXTENDED);
+}
+
+#[test]
+fn test_blake3_incremental() {
+    use serialize::hex::ToHex;
+    let input = test_input(31744);
+    let mut h: Blake3 = HashFn::create();
+    for piece in input.chunks(1000) {
+        h.update(piece);
+    }
+    assert_eq!(h.digest()[].to_hex()[],
+               "62b6960e1a44bcc1eb1a611a8d6235b6b4b78f32e7abc4fb4c6cdcce94895c47");
+}
+
+#[test]
+fn test_blake3_parallel() {
+    use serialize::hex::ToHex;
+    let input = Arc::new(test_input(102400));
+    let hasher: Blake3 = HashFn::create();
+    for &threads in [1u, 2, 3, 8].iter() {
+        assert_eq!(hasher.hash_parallel(input.clone(), threads)[].to_hex()[],
+                   "bc3e3d41a1146b069abffad3c0d44860cf664390afce4d9661f7902e7943e085");
+    }
+}
+
+#[test]
+fn test_blake3_parallel_modes() {
+    let modes = [Blake3::new_keyed(b"whats the Elvish word for friend").unwrap(),
+                 Blake3::new_derive_key("BLAKE3 2019-12-27 16:29:52 test vectors context")];
+    for hasher in modes.iter() {
+        for &len in [1023u, 1024, 1025, 2047, 2048, 2049, 3072, 3073, 5120, 8193].iter() {
+            let input = Arc::new(test_input(len));
+            let mut serial = hasher.clone();
+            serial.update(input.as_slice());
+            let expected = serial.digest();
+            for &threads in [2u, 3, 4, 8].iter() {
+                assert_eq!(hasher.hash_parallel(input.clone(), threads), expected);
+            }
+        }
+    }
+}
@@ -1,89 +1,305 @@
 use cipher::StreamCipher;
+use keystream::{Keystream, Lanes};
+use securemem::SecureMem;
 
-pub struct ChaCha20 {
-    state: [u32, ..16],
-    blocks: [u8, ..64],
-    index: u8,
-}
-
-impl ChaCha20 {
-    pub fn new(key: &[u8], nonce: &[u8]) -> ChaCha20 {
-        let mut chacha = ChaCha20 {
-            state: [
-                0x61707865,
-                0x3320646e,
-                0x79622d32,
-                0x6b206574,
-                unpack!(key, 0),
-                unpack!(key, 1),
-                unpack!(key, 2),
-                unpack!(key, 3),
-                unpack!(key, 4),
-                unpack!(key, 5),
-                unpack!(key, 6),
-                unpack!(key, 7),
-                0,
-                0,
-                unpack!(nonce, 0),
-                unpack!(nonce, 1),
-                ],
-            blocks: [0, ..64], // uninit()?
-            index: 0,
-        };
-        chacha.advance();
-        return chacha;
-    }
-    fn run_all_rounds(&mut self) {
-        let mut state = self.state;
-        for _ in range(0i, 10) {
-            cc20_qround!(state, 0, 4,  8, 12);
-            cc20_qround!(state, 1, 5,  9, 13);
-            cc20_qround!(state, 2, 6, 10, 14);
-            cc20_qround!(state, 3, 7, 11, 15);
-            cc20_qround!(state, 0, 5, 10, 15);
-            cc20_qround!(state, 1, 6, 11, 12);
-            cc20_qround!(state, 2, 7,  8, 13);
-            cc20_qround!(state, 3, 4,  9, 14);
-        }
-        for i in range(0u, 16) {
-            state[i] += self.state[i];
-        }
-        self.blocks = transmute_array(state);
-    }
-    fn advance(&mut self) {
-        self.run_all_rounds();
-        self.index = 0;
-        let i = self.state[12] + 1;
-        self.state[12] = i;
-        if i == 0 {
-            self.state[13] += 1;
+use std::simd::u32x4;
+#[cfg(target_arch = "x86_64")]
+use std::sync::atomic::{AtomicUint, INIT_ATOMIC_UINT, Ordering};
+
+/// The constants, then the key, then `tail`: the counter and nonce words.
+fn initial_state(key: &[u8], tail: [u32, ..4]) -> [u32, ..16] {
+    [
+        0x61707865,
+        0x3320646e,
+        0x79622d32,
+        0x6b206574,
+        unpack!(key, 0),
+        unpack!(key, 1),
+        unpack!(key, 2),
+        unpack!(key, 3),
+        unpack!(key, 4),
+        unpack!(key, 5),
+        unpack!(key, 6),
+        unpack!(key, 7),
+        tail[0],
+        tail[1],
+        tail[2],
+        tail[3],
+        ]
+}
+
+/// The ChaCha permutation, without the final addition of the input.
+fn permute(state: &mut [u32, ..16], double_rounds: uint) {
+    for _ in range(0, double_rounds) {
+        cc20_qround!(state, 0, 4,  8, 12);
+        cc20_qround!(state, 1, 5,  9, 13);
+        cc20_qround!(state, 2, 6, 10, 14);
+        cc20_qround!(state, 3, 7, 11, 15);
+        cc20_qround!(state, 0, 5, 10, 15);
+        cc20_qround!(state, 1, 6, 11, 12);
+        cc20_qround!(state, 2, 7,  8, 13);
+        cc20_qround!(state, 3, 4,  9, 14);
+    }
+}
+
+/// `permute` on four states at once.
+fn permute4(state: &mut [u32x4, ..16], double_rounds: uint) {
+    for _ in range(0, double_rounds) {
+        cc20_qround_lanes!(state, 0, 4,  8, 12);
+        cc20_qround_lanes!(state, 1, 5,  9, 13);
+        cc20_qround_lanes!(state, 2, 6, 10, 14);
+        cc20_qround_lanes!(state, 3, 7, 11, 15);
+        cc20_qround_lanes!(state, 0, 5, 10, 15);
+        cc20_qround_lanes!(state, 1, 6, 11, 12);
+        cc20_qround_lanes!(state, 2, 7,  8, 13);
+        cc20_qround_lanes!(state, 3, 4,  9, 14);
+    }
+}
+
+/// One quarter-round on rows `a`, `b`, `c` and `d` of eight states, in AVX2
+/// registers. Each rotation is a shift each way and an OR.
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+fn qround8(x: &mut [[u32, ..8], ..16], a: uint, b: uint, c: uint, d: uint) {
+    let p = x.as_mut_ptr();
+    unsafe {
+        asm!("vmovdqu ($0), %ymm0
+              vmovdqu ($1), %ymm1
+              vmovdqu ($2), %ymm2
+              vmovdqu ($3), %ymm3
+              vpaddd %ymm1, %ymm0, %ymm0
+              vpxor %ymm0, %ymm3, %ymm3
+              vpslld $$16, %ymm3, %ymm4
+              vpsrld $$16, %ymm3, %ymm3
+              vpor %ymm4, %ymm3, %ymm3
+              vpaddd %ymm3, %ymm2, %ymm2
+              vpxor %ymm2, %ymm1, %ymm1
+              vpslld $$12, %ymm1, %ymm4
+              vpsrld $$20, %ymm1, %ymm1
+              vpor %ymm4, %ymm1, %ymm1
+              vpaddd %ymm1, %ymm0, %ymm0
+              vpxor %ymm0, %ymm3, %ymm3
+              vpslld $$8, %ymm3, %ymm4
+              vpsrld $$24, %ymm3, %ymm3
+              vpor %ymm4, %ymm3, %ymm3
+              vpaddd %ymm3, %ymm2, %ymm2
+              vpxor %ymm2, %ymm1, %ymm1
+              vpslld $$7, %ymm1, %ymm4
+              vpsrld $$25, %ymm1, %ymm1
+              vpor %ymm4, %ymm1, %ymm1
+              vmovdqu %ymm0, ($0)
+              vmovdqu %ymm1, ($1)
+              vmovdqu %ymm2, ($2)
+              vmovdqu %ymm3, ($3)"
+             :
+             : "r"(p.offset(a as int)), "r"(p.offset(b as int)),
+               "r"(p.offset(c as int)), "r"(p.offset(d as int))
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "memory"
+             : "volatile");
+    }
+}
+
+/// `permute` on eight states at once. This needs AVX2, so it's only used
+/// once `has_avx2` has said yes.
+#[cfg(target_arch = "x86_64")]
+fn permute8(state: &mut [[u32, ..8], ..16], double_rounds: uint) {
+    for _ in range(0, double_rounds) {
+        qround8(state, 0, 4,  8, 12);
+        qround8(state, 1, 5,  9, 13);
+        qround8(state, 2, 6, 10, 14);
+        qround8(state, 3, 7, 11, 15);
+        qround8(state, 0, 5, 10, 15);
+        qround8(state, 1, 6, 11, 12);
+        qround8(state, 2, 7,  8, 13);
+        qround8(state, 3, 4,  9, 14);
+    }
+    // Leaving the upper halves dirty would slow down any SSE code after us.
+    unsafe {
+        asm!("vzeroupper" : : : : "volatile");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn cpuid(leaf: u32, subleaf: u32) -> [u32, ..4] {
+    let (a, b, c, d): (u32, u32, u32, u32);
+    unsafe {
+        asm!("cpuid"
+             : "={eax}"(a), "={ebx}"(b), "={ecx}"(c), "={edx}"(d)
+             : "{eax}"(leaf), "{ecx}"(subleaf)
+             :
+             : "volatile");
+    }
+    [a, b, c, d]
+}
+
+/// Whether the CPU has AVX2 and the OS saves the YMM registers across
+/// context switches.
+#[cfg(target_arch = "x86_64")]
+fn detect_avx2() -> bool {
+    if cpuid(0, 0)[0] < 7 {
+        return false;
+    }
+    // OSXSAVE and AVX, then XCR0 for the XMM and YMM state.
+    let ecx = cpuid(1, 0)[2];
+    if ecx & (1 << 27) == 0 || ecx & (1 << 28) == 0 {
+        return false;
+    }
+    let (xcr0, _xcr0_high): (u32, u32);
+    unsafe {
+        asm!("xgetbv" : "={eax}"(xcr0), "={edx}"(_xcr0_high) : "{ecx}"(0u32) : : "volatile");
+    }
+    xcr0 & 6 == 6 && cpuid(7, 0)[1] & (1 << 5) != 0
+}
+
+/// `detect_avx2`, run once per process and then remembered.
+#[cfg(target_arch = "x86_64")]
+fn has_avx2() -> bool {
+    static AVX2: AtomicUint = INIT_ATOMIC_UINT;
+    match AVX2.load(Ordering::Relaxed) {
+        0 => {
+            let yes = detect_avx2();
+            AVX2.store(if yes { 2 } else { 1 }, Ordering::Relaxed);
+            yes
         }
+        n => n == 2,
     }
 }
 
-#[cfg(target_endian = "little")]
-fn transmute_array(input: [u32, ..16]) -> [u8, ..64] {
-    unsafe { ::std::mem::transmute(input) }
+/// The widest multi-block path this CPU can run, which each stream keeps
+/// from then on. AVX2 makes eight blocks at a time. Without it, four still
+/// fit in SSE2's 128-bit registers, which every x86_64 CPU has. Elsewhere
+/// LLVM might have to split the vectors back into scalars, so single blocks
+/// are just as fast.
+#[cfg(target_arch = "x86_64")]
+fn lanes() -> Lanes {
+    if has_avx2() { Lanes::Eight(permute8) } else { Lanes::Four(permute4) }
 }
 
-impl StreamCipher for ChaCha20 {
-    fn combine(&mut self, input: &[u8], output: &mut [u8]) {
-        for (o, &i) in output.iter_mut().zip(input.iter()) {
-            *o = self.blocks[self.index as uint] ^ i;
-            self.index += 1;
-            if self.index == 64 {
-                self.advance();
+#[cfg(not(target_arch = "x86_64"))]
+fn lanes() -> Lanes {
+    Lanes::One
+}
+
+macro_rules! chacha_impl (
+    ($name: ident, $double_rounds: expr) => (
+        pub struct $name {
+            keystream: Keystream,
+        }
+
+        impl $name {
+            /// The original layout: a 64-bit block counter starting at zero
+            /// and a 64-bit nonce. Returns None unless the key is 32 bytes
+            /// and the nonce is 8.
+            pub fn new(key: &[u8], nonce: &[u8]) -> Option<$name> {
+                if key.len() != 32 || nonce.len() != 8 {
+                    return None;
+                }
+                let state = initial_state(key, [0, 0, unpack!(nonce, 0), unpack!(nonce, 1)]);
+                let keystream = Keystream::new(state, 12, false, permute, lanes(),
+                                               $double_rounds);
+                Some($name { keystream: keystream })
+            }
+
+            /// The RFC 8439 layout: a 32-bit block counter starting at
+            /// `counter`, followed by a 96-bit nonce. Returns None unless
+            /// the key is 32 bytes and the nonce is 12.
+            ///
+            /// This allows at most 256 GiB of keystream per nonce; running
+            /// past the end panics rather than wrapping the counter.
+            pub fn new_ietf(key: &[u8], nonce: &[u8], counter: u32) -> Option<$name> {
+                if key.len() != 32 || nonce.len() != 12 {
+                    return None;
+                }
+                let tail = [counter, unpack!(nonce, 0), unpack!(nonce, 1), unpack!(nonce, 2)];
+                let state = initial_state(key, tail);
+                let keystream = Keystream::new(state, 12, true, permute, lanes(),
+                                               $double_rounds);
+                Some($name { keystream: keystream })
+            }
+
+            /// Moves to `offset` bytes into the keystream. Offsets count
+            /// from block 0, so after `new_ietf` with an initial counter `c`
+            /// the stream starts at `64 * c`.
+            ///
+            /// Panics if an IETF stream's one-word counter can't reach
+            /// `offset`.
+            pub fn seek(&mut self, offset: u64) {
+                self.keystream.seek(offset);
+            }
+
+            /// The offset of the next keystream byte, as `seek` takes it.
+            pub fn current_position(&self) -> u64 {
+                self.keystream.current_position()
             }
         }
+
+        impl StreamCipher for $name {
+            fn combine(&mut self, input: &[u8], output: &mut [u8]) {
+                self.keystream.combine(input, output);
+            }
+        }
+    )
+)
+
+chacha_impl!(ChaCha8, 4)
+chacha_impl!(ChaCha12, 6)
+chacha_impl!(ChaCha20, 10)
+
+/// HChaCha20: derives a 32-byte subkey from a 32-byte key and a 16-byte
+/// nonce. It's ChaCha20's rounds without the feed-forward, keeping only the
+/// first and last rows, so the output is as unpredictable as a keystream
+/// block. Returns None if either length is wrong.
+pub fn hchacha20(key: &[u8], nonce: &[u8]) -> Option<Vec<u8>> {
+    if key.len() != 32 || nonce.len() != 16 {
+        return None;
+    }
+    let tail = [unpack!(nonce, 0), unpack!(nonce, 1), unpack!(nonce, 2), unpack!(nonce, 3)];
+    let mut state = initial_state(key, tail);
+    permute(&mut state, 10);
+
+    let mut out = Vec::from_elem(32, 0u8);
+    for (i, w) in range(0u, 4).chain(range(12u, 16)).enumerate() {
+        store_32l!(state[w], out[mut 4*i..]);
+    }
+    for w in state.iter_mut() {
+        *w = 0;
     }
+    Some(out)
 }
 
-impl Drop for ChaCha20 {
-    #[inline(never)]
-    fn drop(&mut self) {
-        for b in self.state.iter_mut() {
-            *b = 0;
+/// XChaCha20: ChaCha20 with a 192-bit nonce, which is large enough to pick
+/// at random. The key and the first 16 nonce bytes make a subkey through
+/// HChaCha20, which is used with the last 8 nonce bytes.
+pub struct XChaCha20 {
+    inner: ChaCha20,
+}
+
+impl XChaCha20 {
+    /// Returns None unless the key is 32 bytes and the nonce is 24.
+    pub fn new(key: &[u8], nonce: &[u8]) -> Option<XChaCha20> {
+        if nonce.len() != 24 {
+            return None;
         }
+        let subkey = match hchacha20(key, nonce[..16]) {
+            Some(subkey) => SecureMem::new(subkey),
+            None => return None,
+        };
+        ChaCha20::new(subkey.as_slice(), nonce[16..]).map(|inner| XChaCha20 { inner: inner })
+    }
+
+    /// Like `ChaCha20::seek`.
+    pub fn seek(&mut self, offset: u64) {
+        self.inner.seek(offset);
+    }
+
+    pub fn current_position(&self) -> u64 {
+        self.inner.current_position()
+    }
+}
+
+impl StreamCipher for XChaCha20 {
+    fn combine(&mut self, input: &[u8], output: &mut [u8]) {
+        self.inner.combine(input, output);
     }
 }
 
@@ -97,7 +313,7 @@ fn test_chacha() {
                                    NONCE.from_hex().unwrap(),
                                    KEYSTREAM.from_hex().unwrap());
 
-    let mut chacha = ChaCha20::new(key[], nonce[]);
+    let mut chacha = ChaCha20::new(key[], nonce[]).unwrap();
     let output = chacha.encrypt(Vec::from_elem(keystream.len(), 0u8).as_slice());
     assert_eq!(output.as_slice(), keystream.as_slice());
 }
@@ -112,7 +328,261 @@ fn test_chacha2() {
                                    NONCE.from_hex().unwrap(),
                                    KEYSTREAM.from_hex().unwrap());
 
-    let mut chacha = ChaCha20::new(key[], nonce[]);
+    let mut chacha = ChaCha20::new(key[], nonce[]).unwrap();
     let output = chacha.encrypt(Vec::from_elem(keystream.len(), 0u8).as_slice());
     assert_eq!(output.as_slice(), keystream.as_slice());
 }
+
+#[test]
+fn test_chacha_ietf_rfc8439() {
+    use serialize::hex::{FromHex, ToHex};
+    let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        .from_hex().unwrap();
+
+    // Section 2.3.2
+    let nonce = "000000090000004a00000000".from_hex().unwrap();
+    let mut chacha = ChaCha20::new_ietf(key[], nonce[], 1).unwrap();
+    assert_eq!(chacha.encrypt(&[0u8, ..64])[].to_hex()[],
+               "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+                d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e");
+
+    // Section 2.4.2
+    let nonce = "000000000000004a00000000".from_hex().unwrap();
+    let ptext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip \
+                  for the future, sunscreen would be it.";
+    let mut chacha = ChaCha20::new_ietf(key[], nonce[], 1).unwrap();
+    assert_eq!(chacha.encrypt(ptext)[].to_hex()[],
+               "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b\
+                f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8\
+                07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736\
+                5af90bbf74a35be6b40b8eedf2785e42874d");
+}
+
+#[test]
+fn test_chacha_bad_lengths() {
+    assert!(ChaCha20::new(&[0u8, ..31], &[0u8, ..8]).is_none());
+    assert!(ChaCha20::new(&[0u8, ..32], &[0u8, ..12]).is_none());
+    assert!(ChaCha20::new(&[0u8, ..32], &[0u8, ..8]).is_some());
+    assert!(ChaCha20::new_ietf(&[0u8, ..33], &[0u8, ..12], 0).is_none());
+    assert!(ChaCha20::new_ietf(&[0u8, ..32], &[0u8, ..8], 0).is_none());
+    assert!(ChaCha20::new_ietf(&[0u8, ..32], &[0u8, ..12], 0).is_some());
+}
+
+#[test]
+fn test_chacha_ietf_last_block() {
+    let mut chacha = ChaCha20::new_ietf(&[0u8, ..32], &[0u8, ..12], 0xffffffff).unwrap();
+    chacha.encrypt(&[0u8, ..64]);
+}
+
+#[test]
+#[should_fail]
+fn test_chacha_ietf_exhausted() {
+    let mut chacha = ChaCha20::new_ietf(&[0u8, ..32], &[0u8, ..12], 0xffffffff).unwrap();
+    chacha.encrypt(&[0u8, ..65]);
+}
+
+#[test]
+fn test_hchacha20() {
+    use serialize::hex::{FromHex, ToHex};
+    // draft-irtf-cfrg-xchacha, section 2.2.1
+    let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        .from_hex().unwrap();
+    let nonce = "000000090000004a0000000031415927".from_hex().unwrap();
+    assert_eq!(hchacha20(key[], nonce[]).unwrap()[].to_hex()[],
+               "82413b4227b27bfed30e42508a877d73a0f9e4d58a74a853c12ec41326d3ecdc");
+    assert!(hchacha20(key[], nonce[..12]).is_none());
+    assert!(hchacha20(key[..16], nonce[]).is_none());
+}
+
+#[test]
+fn test_xchacha20() {
+    use serialize::hex::{FromHex, ToHex};
+    // draft-irtf-cfrg-xchacha, appendix A.3.2
+    let key = "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f"
+        .from_hex().unwrap();
+    let nonce = "404142434445464748494a4b4c4d4e4f5051525354555658".from_hex().unwrap();
+    let ptext = b"The dhole (pronounced \"dole\") is also known as the Asiatic wild dog, red \
+                  dog, and whistling dog. It is about the size of a German shepherd but looks \
+                  more like a long-legged fox. This highly elusive and skilled jumper is \
+                  classified with wolves, coyotes, jackals, and foxes in the taxonomic family \
+                  Canidae.";
+    let mut xchacha = XChaCha20::new(key[], nonce[]).unwrap();
+    assert_eq!(xchacha.encrypt(ptext)[].to_hex()[],
+               "4559abba4e48c16102e8bb2c05e6947f50a786de162f9b0b7e592a9b53d0d4e9\
+                8d8d6410d540a1a6375b26d80dace4fab52384c731acbf16a5923c0c48d3575d\
+                4d0d2c673b666faa731061277701093a6bf7a158a8864292a41c48e3a9b4c0da\
+                ece0f8d98d0d7e05b37a307bbb66333164ec9e1b24ea0d6c3ffddcec4f68e744\
+                3056193a03c810e11344ca06d8ed8a2bfb1e8d48cfa6bc0eb4e2464b74814240\
+                7c9f431aee769960e15ba8b96890466ef2457599852385c661f752ce20f9da0c\
+                09ab6b19df74e76a95967446f8d0fd415e7bee2a12a114c20eb5292ae7a349ae\
+                577820d5520a1f3fb62a17ce6a7e68fa7c79111d8860920bc048ef43fe84486c\
+                cb87c25f0ae045f0cce1e7989a9aa220a28bdd4827e751a24a6d5c62d790a663\
+                93b93111c1a55dd7421a10184974c7c5");
+
+    assert!(XChaCha20::new(key[], nonce[..12]).is_none());
+    assert!(XChaCha20::new(key[..31], nonce[]).is_none());
+}
+
+#[test]
+fn test_chacha_seek() {
+    let key = [0x42u8, ..32];
+    let nonce = [0x24u8, ..8];
+    let mut chacha = ChaCha20::new(&key, &nonce).unwrap();
+    let keystream = chacha.encrypt(&[0u8, ..1000]);
+    assert_eq!(chacha.current_position(), 1000);
+
+    for &(start, len) in [(0u, 1000u), (1, 10), (63, 2), (64, 64), (100, 500), (999, 1)].iter() {
+        chacha.seek(start as u64);
+        assert_eq!(chacha.current_position(), start as u64);
+        assert_eq!(chacha.encrypt(Vec::from_elem(len, 0u8)[])[], keystream[start..start + len]);
+        assert_eq!(chacha.current_position(), (start + len) as u64);
+    }
+
+    // Past the low counter word.
+    let mut far = ChaCha20::new(&key, &nonce).unwrap();
+    far.seek((1 << 32) * 64 - 10);
+    let across = far.encrypt(&[0u8, ..20]);
+    assert_eq!(far.current_position(), (1 << 32) * 64 + 10);
+    far.seek((1 << 32) * 64);
+    assert_eq!(far.encrypt(&[0u8, ..10])[], across[10..]);
+}
+
+#[test]
+fn test_chacha_ietf_seek() {
+    let key = [0x42u8, ..32];
+    let nonce = [0x24u8, ..12];
+    let mut chacha = ChaCha20::new_ietf(&key, &nonce, 1).unwrap();
+    assert_eq!(chacha.current_position(), 64);
+    let keystream = chacha.encrypt(&[0u8, ..300]);
+
+    chacha.seek(64 + 130);
+    assert_eq!(chacha.encrypt(&[0u8, ..170])[], keystream[130..]);
+
+    // The last block can still be reached and used, even after running out.
+    chacha.seek(0xffffffff * 64 + 60);
+    chacha.encrypt(&[0u8, ..4]);
+    assert_eq!(chacha.current_position(), (1 << 32) * 64);
+    chacha.seek(0);
+    assert_eq!(chacha.encrypt(&[0u8, ..64])[].len(), 64);
+}
+
+#[test]
+#[should_fail]
+fn test_chacha_ietf_seek_too_far() {
+    let mut chacha = ChaCha20::new_ietf(&[0u8, ..32], &[0u8, ..12], 0).unwrap();
+    chacha.seek((1 << 32) * 64);
+}
+
+#[test]
+fn test_chacha_reduced_rounds() {
+    use serialize::hex::{FromHex, ToHex};
+    let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        .from_hex().unwrap();
+    let nonce = "000000090000004a00000000".from_hex().unwrap();
+
+    let mut chacha8 = ChaCha8::new_ietf(key[], nonce[], 0).unwrap();
+    assert_eq!(chacha8.encrypt(&[0u8, ..128])[].to_hex()[],
+               "62bf060f2e45c3450b4bc17eefed59cb0c18d2d23ca4bcfc1ced6ba76ed35c95\
+                097b81d752cdc05a91ba6891185c191b110c36cad34db95ec3ea12314329d70e\
+                eead9dfbbc60443e9d6811bab8e60a3ac6001e0dfb985f65efcb0ea42454411c\
+                64747ef73d4766e0c20e19208e5cb11777d487263152e65dc5ff947fcab23b2b");
+
+    let mut chacha12 = ChaCha12::new_ietf(key[], nonce[], 0).unwrap();
+    assert_eq!(chacha12.encrypt(&[0u8, ..128])[].to_hex()[],
+               "15a783e5ad5e601c5a1d3e3db3e8d2405509bcf1c986967825888dc7fd4b991e\
+                e5c8dfb889e3b1ecbe1e474122f60742a28322c85f8390fa8418df439fd50d66\
+                7f8b136677c73799e3e7777d16e6d8ccc787ce39694990c628e087029ce9190b\
+                da4be31ac3fe2102a9ad737cf82fa3b06e68b63371c65c827299040ade1ba8a0");
+}
+
+/// The multi-block path has to agree with single blocks, at any offset and
+/// across a carry into the high counter word.
+#[cfg(test)]
+fn check_lanes(lanes: Lanes) {
+    let state = initial_state(&[0x5au8, ..32], [0xfffffffe, 0, 0x01234567, 0x89abcdef]);
+    let mut one = Keystream::new(state, 12, false, permute, Lanes::One, 10);
+    let mut many = Keystream::new(state, 12, false, permute, lanes, 10);
+    let input = Vec::from_elem(2000, 0x33u8);
+    let (mut a, mut b) = (Vec::from_elem(2000, 0u8), Vec::from_elem(2000, 0u8));
+    one.combine(input[], a[mut]);
+    let mut pos = 0;
+    for &n in [1u, 63, 64, 65, 200, 256, 7, 511, 833].iter() {
+        many.combine(input[pos..pos + n], b[mut pos..pos + n]);
+        pos += n;
+    }
+    assert_eq!(a, b);
+
+    for &offset in [0u64, 5, 64, 255, 256, 511, 512, 1000].iter() {
+        let mut out = [0u8, ..300];
+        many.seek(offset);
+        many.combine(&[0x33u8, ..300], &mut out);
+        assert_eq!(out[], a[offset as uint..offset as uint + 300]);
+        assert_eq!(many.current_position(), offset + 300);
+    }
+
+    // A single-word counter falls back to single blocks near its end.
+    let state = initial_state(&[0x5au8, ..32], [0xfffffffd, 1, 2, 3]);
+    let mut one = Keystream::new(state, 12, true, permute, Lanes::One, 10);
+    let mut many = Keystream::new(state, 12, true, permute, lanes, 10);
+    let (mut a, mut b) = ([0u8, ..192], [0u8, ..192]);
+    one.combine(&[0u8, ..192], &mut a);
+    many.combine(&[0u8, ..192], &mut b);
+    assert_eq!(a[], b[]);
+    assert_eq!(many.current_position(), (1 << 32) * 64);
+}
+
+#[test]
+fn test_chacha_four_blocks() {
+    check_lanes(Lanes::Four(permute4));
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_chacha_eight_blocks() {
+    if has_avx2() {
+        check_lanes(Lanes::Eight(permute8));
+    }
+}
+
+#[test]
+fn test_chacha_portable_block() {
+    use serialize::hex::{FromHex, ToHex};
+    // RFC 8439, section 2.3.2, on the single-block path every host uses.
+    let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        .from_hex().unwrap();
+    let state = initial_state(key[], [1, 0x09000000, 0x4a000000, 0]);
+    let mut ks = Keystream::new(state, 12, true, permute, Lanes::One, 10);
+    let mut block = [0u8, ..64];
+    ks.combine(&[0u8, ..64], &mut block);
+    assert_eq!(block[].to_hex()[],
+               "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+                d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e");
+}
+
+#[cfg(test)]
+fn bench_lanes(b: &mut ::test::Bencher, lanes: Lanes) {
+    let state = initial_state(&[0x5au8, ..32], [0, 0, 1, 2]);
+    let mut ks = Keystream::new(state, 12, false, permute, lanes, 10);
+    let input = [0u8, ..16384];
+    let mut out = [0u8, ..16384];
+    b.bytes = input.len() as u64;
+    b.iter(|| ks.combine(&input, &mut out));
+}
+
+#[bench]
+fn bench_chacha20_one_block(b: &mut ::test::Bencher) {
+    bench_lanes(b, Lanes::One);
+}
+
+#[bench]
+fn bench_chacha20_four_blocks(b: &mut ::test::Bencher) {
+    bench_lanes(b, Lanes::Four(permute4));
+}
+
+#[cfg(target_arch = "x86_64")]
+#[bench]
+fn bench_chacha20_eight_blocks(b: &mut ::test::Bencher) {
+    if has_avx2() {
+        bench_lanes(b, Lanes::Eight(permute8));
+    }
+}
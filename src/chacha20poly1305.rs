@@ -0,0 +1,237 @@
+use chacha20::{ChaCha20, hchacha20};
+use cipher::{AeadError, StreamCipher};
+use poly1305::Poly1305;
+use securemem::SecureMem;
+
+use std::slice::bytes;
+
+pub const KEY_LEN: uint = 32;
+pub const NONCE_LEN: uint = 12;
+pub const XNONCE_LEN: uint = 24;
+pub const TAG_LEN: uint = 16;
+/// The most plaintext one nonce can encrypt: blocks 1 to 2^32 - 1 of the
+/// keystream, since block 0 goes to the Poly1305 key.
+pub const MAX_PTEXT_LEN: u64 = ((1 << 32) - 1) * 64;
+
+/// The ChaCha20-Poly1305 AEAD from RFC 8439. Sealed messages are the
+/// ciphertext followed by a 16-byte tag. The key must be `KEY_LEN` bytes
+/// and the nonce `NONCE_LEN`.
+pub struct ChaCha20Poly1305;
+
+/// Starts the cipher and derives the one-time Poly1305 key from block 0,
+/// which leaves the cipher at block 1, where the message starts. Returns
+/// None if the key or nonce is the wrong length.
+fn setup(key: &[u8], nonce: &[u8]) -> Option<(ChaCha20, Poly1305)> {
+    let mut chacha = match ChaCha20::new_ietf(key, nonce, 0) {
+        Some(chacha) => chacha,
+        None => return None,
+    };
+    let mut block0 = SecureMem::new(Vec::from_elem(64, 0u8));
+    chacha.combine(&[0u8, ..64], block0.as_mut_slice());
+    let poly = Poly1305::new(block0.slice_to(32)).unwrap();
+    Some((chacha, poly))
+}
+
+fn too_long(len: uint) -> bool {
+    len as u64 > MAX_PTEXT_LEN
+}
+
+fn pad16(poly: &mut Poly1305, len: uint) {
+    if len % 16 != 0 {
+        poly.update([0u8, ..16][len % 16..]);
+    }
+}
+
+fn authenticate(poly: &mut Poly1305, aad: &[u8], ctext: &[u8]) {
+    poly.update(aad);
+    pad16(poly, aad.len());
+    poly.update(ctext);
+    pad16(poly, ctext.len());
+
+    let mut lens = [0u8, ..16];
+    store_64l!(aad.len() as u64, lens[mut ..8]);
+    store_64l!(ctext.len() as u64, lens[mut 8..]);
+    poly.update(&lens);
+}
+
+impl ChaCha20Poly1305 {
+    /// Encrypts `ptext` and authenticates it along with `aad`. A nonce must
+    /// never be reused with the same key. Returns None if the key or nonce
+    /// is the wrong length, or `ptext` is longer than `MAX_PTEXT_LEN`.
+    pub fn seal(key: &[u8], nonce: &[u8], aad: &[u8], ptext: &[u8]) -> Option<Vec<u8>> {
+        if too_long(ptext.len()) {
+            return None;
+        }
+        let (mut chacha, mut poly) = match setup(key, nonce) {
+            Some(s) => s,
+            None => return None,
+        };
+        let mut out = chacha.encrypt(ptext);
+        authenticate(&mut poly, aad, out[]);
+        out.push_all(poly.finalize()[]);
+        Some(out)
+    }
+
+    /// Checks the tag and decrypts. Nothing is decrypted unless the tag
+    /// matches.
+    pub fn open(key: &[u8], nonce: &[u8], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, AeadError> {
+        let (mut chacha, mut poly) = match setup(key, nonce) {
+            Some(s) => s,
+            None => return Err(AeadError::InvalidLength),
+        };
+        if sealed.len() < TAG_LEN {
+            return Err(AeadError::TagMismatch);
+        }
+        let (ctext, tag) = (sealed[..sealed.len() - TAG_LEN], sealed[sealed.len() - TAG_LEN..]);
+        if too_long(ctext.len()) {
+            return Err(AeadError::InvalidLength);
+        }
+
+        authenticate(&mut poly, aad, ctext);
+        if !poly.verify(tag) {
+            return Err(AeadError::TagMismatch);
+        }
+        Ok(chacha.decrypt(ctext))
+    }
+}
+
+/// XChaCha20-Poly1305: ChaCha20-Poly1305 with a 192-bit nonce, which can
+/// safely be chosen at random. The key must be `KEY_LEN` bytes and the
+/// nonce `XNONCE_LEN`; otherwise these fail as `ChaCha20Poly1305`'s do.
+pub struct XChaCha20Poly1305;
+
+/// The HChaCha20 subkey and the 96-bit nonce to use with it, or None if the
+/// key or nonce is the wrong length.
+fn xchacha_subkey(key: &[u8], nonce: &[u8]) -> Option<(SecureMem<Vec<u8>>, [u8, ..NONCE_LEN])> {
+    if nonce.len() != XNONCE_LEN {
+        return None;
+    }
+    let subkey = match hchacha20(key, nonce[..16]) {
+        Some(subkey) => SecureMem::new(subkey),
+        None => return None,
+    };
+    let mut inner_nonce = [0u8, ..NONCE_LEN];
+    bytes::copy_memory(inner_nonce[mut 4..], nonce[16..]);
+    Some((subkey, inner_nonce))
+}
+
+impl XChaCha20Poly1305 {
+    pub fn seal(key: &[u8], nonce: &[u8], aad: &[u8], ptext: &[u8]) -> Option<Vec<u8>> {
+        match xchacha_subkey(key, nonce) {
+            Some((subkey, inner_nonce)) =>
+                ChaCha20Poly1305::seal(subkey.as_slice(), &inner_nonce, aad, ptext),
+            None => None,
+        }
+    }
+
+    pub fn open(key: &[u8], nonce: &[u8], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, AeadError> {
+        match xchacha_subkey(key, nonce) {
+            Some((subkey, inner_nonce)) =>
+                ChaCha20Poly1305::open(subkey.as_slice(), &inner_nonce, aad, sealed),
+            None => Err(AeadError::InvalidLength),
+        }
+    }
+}
+
+#[test]
+fn test_chacha20poly1305_rfc8439() {
+    use serialize::hex::{FromHex, ToHex};
+    // Section 2.8.2
+    let key = "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f"
+        .from_hex().unwrap();
+    let nonce = "070000004041424344454647".from_hex().unwrap();
+    let aad = "50515253c0c1c2c3c4c5c6c7".from_hex().unwrap();
+    let ptext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip \
+                  for the future, sunscreen would be it.";
+    let expected = "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+                    3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+                    92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+                    3ff4def08e4b7a9de576d26586cec64b6116\
+                    1ae10b594f09e26a7e902ecbd0600691";
+
+    let sealed = ChaCha20Poly1305::seal(key[], nonce[], aad[], ptext).unwrap();
+    assert_eq!(sealed[].to_hex()[], expected);
+    assert_eq!(ChaCha20Poly1305::open(key[], nonce[], aad[], sealed[]).unwrap()[], ptext);
+
+    // Nothing to encrypt still gets a tag.
+    assert_eq!(ChaCha20Poly1305::seal(key[], nonce[], &[], &[]).unwrap()[].to_hex()[],
+               "a0784d7a4716f3feb4f64e7f4b39bf04");
+    assert_eq!(ChaCha20Poly1305::seal(key[], nonce[], aad[], &[]).unwrap()[].to_hex()[],
+               "e622e5647a38d967a7ecbcb46c7f675c");
+}
+
+#[test]
+fn test_chacha20poly1305_tampering() {
+    let key = [7u8, ..32];
+    let nonce = [1u8, ..12];
+    let sealed = ChaCha20Poly1305::seal(&key, &nonce, b"header", b"attack at dawn").unwrap();
+    assert_eq!(ChaCha20Poly1305::open(&key, &nonce, b"header", sealed[]).unwrap()[],
+               b"attack at dawn");
+
+    for i in range(0, sealed.len()) {
+        let mut bad = sealed.clone();
+        bad[i] ^= 0x80;
+        assert_eq!(ChaCha20Poly1305::open(&key, &nonce, b"header", bad[]),
+                   Err(AeadError::TagMismatch));
+    }
+    assert_eq!(ChaCha20Poly1305::open(&key, &nonce, b"footer", sealed[]),
+               Err(AeadError::TagMismatch));
+    assert_eq!(ChaCha20Poly1305::open(&key, &[2u8, ..12], b"header", sealed[]),
+               Err(AeadError::TagMismatch));
+    assert_eq!(ChaCha20Poly1305::open(&key, &nonce, b"header", sealed[..TAG_LEN - 1]),
+               Err(AeadError::TagMismatch));
+}
+
+#[test]
+fn test_xchacha20poly1305() {
+    use serialize::hex::{FromHex, ToHex};
+    // draft-irtf-cfrg-xchacha, appendix A.3.1
+    let key = "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f"
+        .from_hex().unwrap();
+    let nonce = "404142434445464748494a4b4c4d4e4f5051525354555657".from_hex().unwrap();
+    let aad = "50515253c0c1c2c3c4c5c6c7".from_hex().unwrap();
+    let ptext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip \
+                  for the future, sunscreen would be it.";
+    let expected = "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb\
+                    731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452\
+                    2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9\
+                    21f9664c97637da9768812f615c68b13b52e\
+                    c0875924c1c7987947deafd8780acf49";
+
+    let sealed = XChaCha20Poly1305::seal(key[], nonce[], aad[], ptext).unwrap();
+    assert_eq!(sealed[].to_hex()[], expected);
+    assert_eq!(XChaCha20Poly1305::open(key[], nonce[], aad[], sealed[]).unwrap()[], ptext);
+    assert_eq!(XChaCha20Poly1305::seal(key[], nonce[], &[], &[]).unwrap()[].to_hex()[],
+               "1dac8f73146d1e9da796cb7f7221a5df");
+
+    let mut bad = sealed.clone();
+    bad[0] ^= 1;
+    assert_eq!(XChaCha20Poly1305::open(key[], nonce[], aad[], bad[]),
+               Err(AeadError::TagMismatch));
+}
+
+#[test]
+fn test_bad_lengths() {
+    let key = [7u8, ..KEY_LEN];
+    let nonce = [1u8, ..XNONCE_LEN];
+    let sealed = ChaCha20Poly1305::seal(&key, nonce[..NONCE_LEN], b"", b"hi").unwrap();
+    let xsealed = XChaCha20Poly1305::seal(&key, &nonce, b"", b"hi").unwrap();
+
+    let bad = [(key[..16], nonce[..NONCE_LEN]), (key[], nonce[..8]), (key[], nonce[..0])];
+    for &(k, n) in bad.iter() {
+        assert!(ChaCha20Poly1305::seal(k, n, b"", b"hi").is_none());
+        assert_eq!(ChaCha20Poly1305::open(k, n, b"", sealed[]), Err(AeadError::InvalidLength));
+    }
+    let bad = [(key[..16], nonce[]), (key[], nonce[..NONCE_LEN]), (key[], nonce[..0])];
+    for &(k, n) in bad.iter() {
+        assert!(XChaCha20Poly1305::seal(k, n, b"", b"hi").is_none());
+        assert_eq!(XChaCha20Poly1305::open(k, n, b"", xsealed[]),
+                   Err(AeadError::InvalidLength));
+    }
+
+    // Too long to build here, so check the limit itself.
+    if cfg!(target_word_size = "64") {
+        assert!(!too_long((((1u64 << 32) - 1) * 64) as uint));
+        assert!(too_long(MAX_PTEXT_LEN as uint + 1));
+    }
+}
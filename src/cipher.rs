@@ -113,6 +113,22 @@ pub trait BlockCipher {
     }
 }
 
+/// Lets a mode borrow a cipher instead of owning it, so one key schedule
+/// can serve more than one mode.
+impl<'a, C: BlockCipher> BlockCipher for &'a C {
+    fn block_size(_: Option<&&'a C>) -> uint {
+        BlockCipher::block_size(None::<&C>)
+    }
+
+    fn encrypt_block(&self, input: &[u8], output: &mut [u8]) {
+        (**self).encrypt_block(input, output);
+    }
+
+    fn decrypt_block(&self, input: &[u8], output: &mut [u8]) {
+        (**self).decrypt_block(input, output);
+    }
+}
+
 fn bxor_into(one: &[u8], two: &[u8], out: &mut [u8]) {
     for (o, (&a, &b)) in out.iter_mut().zip(one.iter().zip(two.iter())) {
         *o = a ^ b;
@@ -140,3 +156,600 @@ pub trait StreamCipher {
         return out;
     }
 }
+
+/// Adds `n` to the big-endian number in the last `width` bytes of `block`,
+/// dropping any carry out of them.
+fn add_to_counter(block: &mut [u8], width: uint, n: u64) {
+    let len = block.len();
+    let mut carry = n;
+    for i in range(0, width) {
+        if carry == 0 {
+            break;
+        }
+        let sum = block[len - 1 - i] as u64 + (carry & 0xff);
+        block[len - 1 - i] = sum as u8;
+        carry = (carry >> 8) + (sum >> 8);
+    }
+}
+
+/// CTR mode (SP 800-38A) over any `BlockCipher`, as a `StreamCipher`.
+///
+/// The counter is the last `width` bytes of the block, big-endian, and only
+/// those bytes change; the rest of the initial counter block stays as it
+/// was. A width of the whole block is SP 800-38A's counter, 4 is GCM's, and
+/// 8 on a 16-byte block is a 64-bit nonce followed by a 64-bit counter.
+pub struct Ctr<C> {
+    cipher: C,
+    iv: Vec<u8>,
+    width: uint,
+    /// How many blocks past `iv` the next block is.
+    blocks: u64,
+    /// The block before that one, used up to `index`.
+    keystream: Vec<u8>,
+    index: uint,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    /// `iv` is the initial counter block. Returns None unless it's one
+    /// block long and `width` is between 1 and the block size.
+    pub fn new(cipher: C, iv: &[u8], width: uint) -> Option<Ctr<C>> {
+        let bs = BlockCipher::block_size(None::<&C>);
+        if iv.len() != bs || width == 0 || width > bs {
+            return None;
+        }
+        Some(Ctr {
+            cipher: cipher,
+            iv: iv.to_vec(),
+            width: width,
+            blocks: 0,
+            keystream: Vec::from_elem(bs, 0u8),
+            index: bs,
+        })
+    }
+
+    /// Moves to `offset` bytes into the keystream.
+    ///
+    /// Panics if that's further than the counter can count without
+    /// repeating itself.
+    pub fn seek(&mut self, offset: u64) {
+        let bs = self.keystream.len() as u64;
+        let block = offset / bs;
+        assert!(self.width >= 8 || block <= 1 << (8 * self.width),
+                "CTR seek past the end of the keystream");
+        self.blocks = block;
+        self.index = bs as uint;
+        if offset % bs != 0 {
+            self.next_block();
+            self.index = (offset % bs) as uint;
+        }
+    }
+
+    /// The offset of the next keystream byte, as `seek` takes it.
+    pub fn current_position(&self) -> u64 {
+        let bs = self.keystream.len() as u64;
+        self.blocks * bs + self.index as u64 - bs
+    }
+
+    fn next_block(&mut self) {
+        // A narrow counter wrapping around would repeat keystream.
+        assert!(self.width >= 8 || self.blocks < 1 << (8 * self.width),
+                "CTR counter exhausted");
+        let mut counter = self.iv.clone();
+        add_to_counter(counter[mut], self.width, self.blocks);
+        self.cipher.encrypt_block(counter[], self.keystream[mut]);
+        self.blocks += 1;
+        self.index = 0;
+    }
+}
+
+impl<C: BlockCipher> StreamCipher for Ctr<C> {
+    fn combine(&mut self, input: &[u8], output: &mut [u8]) {
+        let bs = self.keystream.len();
+        for (o, &i) in output.iter_mut().zip(input.iter()) {
+            if self.index == bs {
+                self.next_block();
+            }
+            *o = self.keystream[self.index] ^ i;
+            self.index += 1;
+        }
+    }
+}
+
+#[unsafe_destructor]
+impl<C> Drop for Ctr<C> {
+    #[inline(never)]
+    fn drop(&mut self) {
+        for b in self.keystream.iter_mut() {
+            *b = 0;
+        }
+    }
+}
+
+/// The feedback register and keystream shared by `CfbEncryptor` and
+/// `CfbDecryptor`, which only differ in which side of the XOR is fed back.
+struct Cfb<C> {
+    cipher: C,
+    /// The last block's worth of ciphertext, starting as the IV.
+    register: Vec<u8>,
+    /// The encrypted register. Full-block segments use it up to `index`;
+    /// smaller ones take a fresh one for every segment.
+    keystream: Vec<u8>,
+    index: uint,
+    segment_bits: uint,
+}
+
+impl<C: BlockCipher> Cfb<C> {
+    fn new(cipher: C, iv: &[u8], segment_bits: uint) -> Option<Cfb<C>> {
+        let bs = BlockCipher::block_size(None::<&C>);
+        if iv.len() != bs || (segment_bits != 1 && segment_bits != 8 && segment_bits != 8 * bs) {
+            return None;
+        }
+        Some(Cfb {
+            cipher: cipher,
+            register: iv.to_vec(),
+            keystream: Vec::from_elem(bs, 0u8),
+            index: bs,
+            segment_bits: segment_bits,
+        })
+    }
+
+    fn combine(&mut self, input: &[u8], output: &mut [u8], decrypting: bool) {
+        for (o, &i) in output.iter_mut().zip(input.iter()) {
+            *o = match self.segment_bits {
+                1 => self.combine_bits(i, decrypting),
+                8 => self.combine_byte(i, decrypting),
+                _ => self.combine_block_byte(i, decrypting),
+            };
+        }
+    }
+
+    fn combine_block_byte(&mut self, input: u8, decrypting: bool) -> u8 {
+        if self.index == self.register.len() {
+            self.cipher.encrypt_block(self.register[], self.keystream[mut]);
+            self.index = 0;
+        }
+        let output = input ^ self.keystream[self.index];
+        self.register[self.index] = if decrypting { input } else { output };
+        self.index += 1;
+        output
+    }
+
+    fn combine_byte(&mut self, input: u8, decrypting: bool) -> u8 {
+        self.cipher.encrypt_block(self.register[], self.keystream[mut]);
+        let output = input ^ self.keystream[0];
+        let bs = self.register.len();
+        for i in range(0, bs - 1) {
+            self.register[i] = self.register[i + 1];
+        }
+        self.register[bs - 1] = if decrypting { input } else { output };
+        output
+    }
+
+    /// One byte as eight 1-bit segments, most significant bit first.
+    fn combine_bits(&mut self, input: u8, decrypting: bool) -> u8 {
+        let mut output = 0u8;
+        for shift in range(0u, 8).rev() {
+            output |= self.combine_bit((input >> shift) & 1, decrypting) << shift;
+        }
+        output
+    }
+
+    /// A single 1-bit segment; `input` is 0 or 1.
+    fn combine_bit(&mut self, input: u8, decrypting: bool) -> u8 {
+        let bs = self.register.len();
+        self.cipher.encrypt_block(self.register[], self.keystream[mut]);
+        let output = input ^ (self.keystream[0] >> 7);
+        let fed_back = if decrypting { input } else { output };
+        for i in range(0, bs - 1) {
+            self.register[i] = (self.register[i] << 1) | (self.register[i + 1] >> 7);
+        }
+        self.register[bs - 1] = (self.register[bs - 1] << 1) | fed_back;
+        output
+    }
+}
+
+#[unsafe_destructor]
+impl<C> Drop for Cfb<C> {
+    #[inline(never)]
+    fn drop(&mut self) {
+        for b in self.register.iter_mut().chain(self.keystream.iter_mut()) {
+            *b = 0;
+        }
+    }
+}
+
+/// CFB mode (SP 800-38A) encryption over any `BlockCipher`. Messages can be
+/// fed in pieces of any length; `combine` (and so both `encrypt` and
+/// `decrypt`) always encrypts.
+///
+/// The segment size is 1 bit, 8 bits or the whole block, which are CFB1,
+/// CFB8 and CFB128 for AES. CFB1 takes bytes a bit at a time, most
+/// significant first, and costs a block encryption per bit.
+pub struct CfbEncryptor<C> {
+    cfb: Cfb<C>,
+}
+
+impl<C: BlockCipher> CfbEncryptor<C> {
+    /// Returns None unless `iv` is one block long and `segment_bits` is 1,
+    /// 8 or the block size in bits.
+    pub fn new(cipher: C, iv: &[u8], segment_bits: uint) -> Option<CfbEncryptor<C>> {
+        Cfb::new(cipher, iv, segment_bits).map(|cfb| CfbEncryptor { cfb: cfb })
+    }
+}
+
+impl<C: BlockCipher> StreamCipher for CfbEncryptor<C> {
+    fn combine(&mut self, input: &[u8], output: &mut [u8]) {
+        self.cfb.combine(input, output, false);
+    }
+}
+
+/// The decrypting side of `CfbEncryptor`; `combine` always decrypts.
+pub struct CfbDecryptor<C> {
+    cfb: Cfb<C>,
+}
+
+impl<C: BlockCipher> CfbDecryptor<C> {
+    /// Takes the same arguments as `CfbEncryptor::new`.
+    pub fn new(cipher: C, iv: &[u8], segment_bits: uint) -> Option<CfbDecryptor<C>> {
+        Cfb::new(cipher, iv, segment_bits).map(|cfb| CfbDecryptor { cfb: cfb })
+    }
+}
+
+impl<C: BlockCipher> StreamCipher for CfbDecryptor<C> {
+    fn combine(&mut self, input: &[u8], output: &mut [u8]) {
+        self.cfb.combine(input, output, true);
+    }
+}
+
+/// OFB mode (SP 800-38A) over any `BlockCipher`, as a `StreamCipher`: the
+/// keystream is the IV encrypted over and over.
+pub struct Ofb<C> {
+    cipher: C,
+    /// The latest block of keystream, used up to `index`; the IV before the
+    /// first.
+    keystream: Vec<u8>,
+    index: uint,
+}
+
+impl<C: BlockCipher> Ofb<C> {
+    /// Returns None unless `iv` is one block long.
+    pub fn new(cipher: C, iv: &[u8]) -> Option<Ofb<C>> {
+        if iv.len() != BlockCipher::block_size(None::<&C>) {
+            return None;
+        }
+        Some(Ofb { cipher: cipher, keystream: iv.to_vec(), index: iv.len() })
+    }
+}
+
+impl<C: BlockCipher> StreamCipher for Ofb<C> {
+    fn combine(&mut self, input: &[u8], output: &mut [u8]) {
+        let bs = self.keystream.len();
+        for (o, &i) in output.iter_mut().zip(input.iter()) {
+            if self.index == bs {
+                let prev = self.keystream.clone();
+                self.cipher.encrypt_block(prev[], self.keystream[mut]);
+                self.index = 0;
+            }
+            *o = self.keystream[self.index] ^ i;
+            self.index += 1;
+        }
+    }
+}
+
+#[unsafe_destructor]
+impl<C> Drop for Ofb<C> {
+    #[inline(never)]
+    fn drop(&mut self) {
+        for b in self.keystream.iter_mut() {
+            *b = 0;
+        }
+    }
+}
+
+/// Why an AEAD refused to open a ciphertext.
+#[deriving(Clone, PartialEq, Eq, Show)]
+pub enum AeadError {
+    /// The tag didn't match, or the input was too short to hold one. Either
+    /// way nothing about the plaintext is revealed.
+    TagMismatch,
+    /// The key or nonce was the wrong length.
+    InvalidLength,
+}
+
+#[cfg(test)]
+static SP800_38A_PTEXT: &'static str =
+    "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+     30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+#[test]
+fn test_ctr_sp800_38a() {
+    use aes::{Aes, Aes128, Aes192, Aes256};
+    use serialize::hex::{FromHex, ToHex};
+    // F.5.1 to F.5.6
+    let iv = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".from_hex().unwrap();
+    let ptext = SP800_38A_PTEXT.from_hex().unwrap();
+
+    let key = "2b7e151628aed2a6abf7158809cf4f3c".from_hex().unwrap();
+    let expected = "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+                    5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee";
+    let aes: Aes128 = Aes::new(key[]).unwrap();
+    let ctext = Ctr::new(aes, iv[], 16).unwrap().encrypt(ptext[]);
+    assert_eq!(ctext[].to_hex()[], expected);
+    let aes: Aes128 = Aes::new(key[]).unwrap();
+    assert_eq!(Ctr::new(aes, iv[], 16).unwrap().decrypt(ctext[]), ptext);
+
+    let key = "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b".from_hex().unwrap();
+    let expected = "1abc932417521ca24f2b0459fe7e6e0b090339ec0aa6faefd5ccc2c6f4ce8e94\
+                    1e36b26bd1ebc670d1bd1d665620abf74f78a7f6d29809585a97daec58c6b050";
+    let aes: Aes192 = Aes::new(key[]).unwrap();
+    assert_eq!(Ctr::new(aes, iv[], 16).unwrap().encrypt(ptext[])[].to_hex()[], expected);
+
+    let key = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4"
+        .from_hex().unwrap();
+    let expected = "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+                    2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6";
+    let aes: Aes256 = Aes::new(key[]).unwrap();
+    assert_eq!(Ctr::new(aes, iv[], 16).unwrap().encrypt(ptext[])[].to_hex()[], expected);
+}
+
+#[test]
+fn test_ctr_width_and_seek() {
+    use aes::{Aes, Aes128};
+    use serialize::hex::{FromHex, ToHex};
+    let key = "2b7e151628aed2a6abf7158809cf4f3c".from_hex().unwrap();
+    let iv = "000102030405060708090a0bffffffff".from_hex().unwrap();
+
+    // A 32-bit counter wraps without carrying into the rest of the block;
+    // a full-width one carries.
+    let aes: Aes128 = Aes::new(key[]).unwrap();
+    assert_eq!(Ctr::new(aes, iv[], 4).unwrap().encrypt(&[0u8, ..32])[].to_hex()[],
+               "bdb7c0ef49717942fc68eeb17692fcf494193f8116eb745cfe7465d70c756236");
+    let aes: Aes128 = Aes::new(key[]).unwrap();
+    assert_eq!(Ctr::new(aes, iv[], 16).unwrap().encrypt(&[0u8, ..32])[].to_hex()[],
+               "bdb7c0ef49717942fc68eeb17692fcf4eef89e9494c1082ab27d4d9095feff60");
+
+    let aes: Aes128 = Aes::new(key[]).unwrap();
+    let mut ctr = Ctr::new(aes, iv[], 8).unwrap();
+    let keystream = ctr.encrypt(&[0u8, ..100]);
+    assert_eq!(ctr.current_position(), 100);
+    for &offset in [0u, 1, 15, 16, 17, 50, 99].iter() {
+        ctr.seek(offset as u64);
+        assert_eq!(ctr.current_position(), offset as u64);
+        assert_eq!(ctr.encrypt(Vec::from_elem(100 - offset, 0u8)[])[], keystream[offset..]);
+    }
+
+    let aes: Aes128 = Aes::new(key[]).unwrap();
+    assert!(Ctr::new(aes, iv[..8], 4).is_none());
+    let aes: Aes128 = Aes::new(key[]).unwrap();
+    assert!(Ctr::new(aes, iv[], 0).is_none());
+    let aes: Aes128 = Aes::new(key[]).unwrap();
+    assert!(Ctr::new(aes, iv[], 17).is_none());
+}
+
+#[cfg(test)]
+fn check_cfb<C: BlockCipher>(enc: C, dec: C, iv: &str, segment_bits: uint, ptext: &str,
+                             expected: &str) {
+    use serialize::hex::{FromHex, ToHex};
+    let iv = iv.from_hex().unwrap();
+    let ptext = ptext.from_hex().unwrap();
+    let ctext = CfbEncryptor::new(enc, iv[], segment_bits).unwrap().encrypt(ptext[]);
+    assert_eq!(ctext[].to_hex()[], expected);
+    assert_eq!(CfbDecryptor::new(dec, iv[], segment_bits).unwrap().decrypt(ctext[]), ptext);
+}
+
+#[test]
+fn test_cfb_sp800_38a() {
+    use aes::{Aes, Aes128, Aes256};
+    use serialize::hex::FromHex;
+    let iv = "000102030405060708090a0b0c0d0e0f";
+    let key128 = "2b7e151628aed2a6abf7158809cf4f3c".from_hex().unwrap();
+    let key256 = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4"
+        .from_hex().unwrap();
+    let aes128 = || -> Aes128 { Aes::new(key128[]).unwrap() };
+    let aes256 = || -> Aes256 { Aes::new(key256[]).unwrap() };
+
+    // F.3.1 and F.3.5: the 16 bits 0110101111000001.
+    check_cfb(aes128(), aes128(), iv, 1, "6bc1", "68b3");
+    check_cfb(aes256(), aes256(), iv, 1, "6bc1", "9029");
+
+    // F.3.7 and F.3.11
+    check_cfb(aes128(), aes128(), iv, 8, SP800_38A_PTEXT[..36],
+              "3b79424c9c0dd436bace9e0ed4586a4f32b9");
+    check_cfb(aes256(), aes256(), iv, 8, SP800_38A_PTEXT[..36],
+              "dc1f1a8520a64db55fcc8ac554844e889700");
+
+    // F.3.13 and F.3.17
+    check_cfb(aes128(), aes128(), iv, 128, SP800_38A_PTEXT,
+              "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+               26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6");
+    check_cfb(aes256(), aes256(), iv, 128, SP800_38A_PTEXT,
+              "dc7e84bfda79164b7ecd8486985d386039ffed143b28b1c832113c6331e5407b\
+               df10132415e54b92a13ed0a8267ae2f975a385741ab9cef82031623d55b1e471");
+
+    assert!(CfbEncryptor::new(aes128(), iv.from_hex().unwrap()[], 16).is_none());
+    assert!(CfbDecryptor::new(aes128(), iv.from_hex().unwrap()[], 64).is_none());
+    assert!(CfbEncryptor::new(aes128(), key256[], 8).is_none());
+}
+
+#[test]
+fn test_ofb_sp800_38a() {
+    use aes::{Aes, Aes128, Aes256};
+    use serialize::hex::{FromHex, ToHex};
+    // F.4.1 and F.4.5
+    let iv = "000102030405060708090a0b0c0d0e0f".from_hex().unwrap();
+    let ptext = SP800_38A_PTEXT.from_hex().unwrap();
+
+    let key = "2b7e151628aed2a6abf7158809cf4f3c".from_hex().unwrap();
+    let expected = "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+                    9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e";
+    let aes: Aes128 = Aes::new(key[]).unwrap();
+    let ctext = Ofb::new(aes, iv[]).unwrap().encrypt(ptext[]);
+    assert_eq!(ctext[].to_hex()[], expected);
+    let aes: Aes128 = Aes::new(key[]).unwrap();
+    assert_eq!(Ofb::new(aes, iv[]).unwrap().decrypt(ctext[]), ptext);
+
+    let key = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4"
+        .from_hex().unwrap();
+    let expected = "dc7e84bfda79164b7ecd8486985d38604febdc6740d20b3ac88f6ad82a4fb08d\
+                    71ab47a086e86eedf39d1c5bba97c4080126141d67f37be8538f5a8be740e484";
+    let aes: Aes256 = Aes::new(key[]).unwrap();
+    assert_eq!(Ofb::new(aes, iv[]).unwrap().encrypt(ptext[])[].to_hex()[], expected);
+
+    let aes: Aes256 = Aes::new(key[]).unwrap();
+    assert!(Ofb::new(aes, iv[..8]).is_none());
+}
+
+#[test]
+fn test_cfb_ofb_incremental() {
+    use aes::{Aes, Aes128};
+    use serialize::hex::FromHex;
+    let key = "2b7e151628aed2a6abf7158809cf4f3c".from_hex().unwrap();
+    let iv = "000102030405060708090a0b0c0d0e0f".from_hex().unwrap();
+    let ptext = SP800_38A_PTEXT.from_hex().unwrap();
+    let aes = || -> Aes128 { Aes::new(key[]).unwrap() };
+
+    // Feeding a message in odd-sized pieces changes nothing.
+    for &bits in [1u, 8, 128].iter() {
+        let whole = CfbEncryptor::new(aes(), iv[], bits).unwrap().encrypt(ptext[]);
+        let mut enc = CfbEncryptor::new(aes(), iv[], bits).unwrap();
+        let mut dec = CfbDecryptor::new(aes(), iv[], bits).unwrap();
+        let mut pieces = Vec::new();
+        let mut back = Vec::new();
+        for chunk in ptext[].chunks(7) {
+            let ctext = enc.encrypt(chunk);
+            back.push_all(dec.decrypt(ctext[])[]);
+            pieces.push_all(ctext[]);
+        }
+        assert_eq!(pieces, whole);
+        assert_eq!(back, ptext);
+    }
+
+    let whole = Ofb::new(aes(), iv[]).unwrap().encrypt(ptext[]);
+    let mut ofb = Ofb::new(aes(), iv[]).unwrap();
+    let mut pieces = Vec::new();
+    for chunk in ptext[].chunks(5) {
+        pieces.push_all(ofb.encrypt(chunk)[]);
+    }
+    assert_eq!(pieces, whole);
+}
+
+/// `bytes` as one bit per byte, most significant first.
+#[cfg(test)]
+fn to_bits(bytes: &[u8]) -> Vec<u8> {
+    let mut bits = Vec::with_capacity(8 * bytes.len());
+    for &b in bytes.iter() {
+        for shift in range(0u, 8).rev() {
+            bits.push((b >> shift) & 1);
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+fn from_bits(bits: &[u8]) -> Vec<u8> {
+    bits.chunks(8).map(|byte| byte.iter().fold(0u8, |acc, &b| (acc << 1) | b)).collect()
+}
+
+/// A segment as the AESAVS files print it: hex, or a lone bit for CFB1.
+#[cfg(test)]
+fn segment_hex(bits: &[u8]) -> String {
+    use serialize::hex::ToHex;
+    if bits.len() == 1 { bits[0].to_string() } else { from_bits(bits)[].to_hex() }
+}
+
+/// The AESAVS Monte Carlo test (section 6.4) for CFB and OFB with 128-bit
+/// keys, encrypting `segment` bits at a time. Segments are passed around a
+/// bit per byte so that CFB1 feeds back single bits: each segment of
+/// plaintext is an earlier one of IV or ciphertext. Returns each of the 100
+/// rounds' KEY, IV, PLAINTEXT and CIPHERTEXT as the response files give them.
+#[cfg(test)]
+fn aesavs_mct<S>(new: |::aes::Aes128, &[u8]| -> S, encrypt: |&mut S, &[u8]| -> Vec<u8>,
+                 segment: uint, key: &str, iv: &str, ptext: &[u8])
+                 -> Vec<(String, String, String, String)> {
+    use aes::{Aes, Aes128};
+    use serialize::hex::{FromHex, ToHex};
+    let mut key = key.from_hex().unwrap();
+    let mut iv = iv.from_hex().unwrap();
+    let mut ptext = ptext.to_vec();
+    let back = 128 / segment;
+    let mut rounds = Vec::new();
+
+    for _ in range(0u, 100) {
+        let (key_hex, iv_hex, ptext_hex) = (key[].to_hex(), iv[].to_hex(), segment_hex(ptext[]));
+        let aes: Aes128 = Aes::new(key[]).unwrap();
+        let mut cipher = new(aes, iv[]);
+        let iv_bits = to_bits(iv[]);
+        let mut ctext = Vec::with_capacity(1000 * segment);
+        for j in range(0u, 1000) {
+            ctext.push_all(encrypt(&mut cipher, ptext[])[]);
+            ptext = if j < back {
+                iv_bits[j*segment..(j+1)*segment].to_vec()
+            } else {
+                ctext[(j-back)*segment..(j-back+1)*segment].to_vec()
+            };
+        }
+        rounds.push((key_hex, iv_hex, ptext_hex, segment_hex(ctext[ctext.len() - segment..])));
+
+        let last = from_bits(ctext[ctext.len() - 128..]);
+        for (k, &c) in key.iter_mut().zip(last.iter()) {
+            *k ^= c;
+        }
+        iv = last;
+    }
+    rounds
+}
+
+#[test]
+fn test_aesavs_mct() {
+    use aes::Aes128;
+    // Checked against an independent run of the same procedure over
+    // OpenSSL's AES-CFB8, CFB128 and OFB, and a bitwise CFB1 that matches
+    // OpenSSL's aes-128-cfb1.
+    let key = "ffeeddccbbaa99887766554433221100";
+    let iv = "00112233445566778899aabbccddeeff";
+    let ptext = "0123456789abcdeffedcba9876543210";
+    let ptext_bits = to_bits(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef,
+                               0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10]);
+    let check = |rounds: &[(String, String, String, String)], count: uint,
+                 expected: [&str, ..4]| {
+        let (ref k, ref v, ref p, ref c) = rounds[count];
+        assert_eq!([k[], v[], p[], c[]], expected);
+    };
+
+    // CFB1 goes through a bit at a time.
+    let cfb1 = aesavs_mct(|aes, iv| CfbEncryptor::new(aes, iv, 1).unwrap(),
+                          |c: &mut CfbEncryptor<Aes128>, bits: &[u8]| {
+                              vec![c.cfb.combine_bit(bits[0], false)]
+                          },
+                          1, key, iv, &[1]);
+    check(cfb1[], 0, [key, iv, "1", "1"]);
+    check(cfb1[], 99, ["0527707cd966b298e6cc1752f6506a5c", "7451714c369bee88010209dba3bca0cd",
+                       "1", "0"]);
+
+    let cfb8 = aesavs_mct(|aes, iv| CfbEncryptor::new(aes, iv, 8).unwrap(),
+                          |c: &mut CfbEncryptor<Aes128>, bits: &[u8]| {
+                              to_bits(c.encrypt(from_bits(bits)[])[])
+                          },
+                          8, key, iv, to_bits(&[0x42])[]);
+    check(cfb8[], 0, [key, iv, "42", "7e"]);
+    check(cfb8[], 99, ["7921ad42f6e9cc5a4cf10c355e99e232", "089add51bc6e4bc56ee89b3960c84274",
+                       "46", "a6"]);
+
+    let cfb128 = aesavs_mct(|aes, iv| CfbEncryptor::new(aes, iv, 128).unwrap(),
+                            |c: &mut CfbEncryptor<Aes128>, bits: &[u8]| {
+                                to_bits(c.encrypt(from_bits(bits)[])[])
+                            },
+                            128, key, iv, ptext_bits[]);
+    check(cfb128[], 0, [key, iv, ptext, "7ca6ecedfa44bb3ba6ac98e89a64df0c"]);
+    check(cfb128[], 99, ["783fd5dd158e427f6129537dcc950955", "e0093cced604009bc5a666992f5604cc",
+                         "05e2a1cb3987a5e18b50dfabe4c22d28", "5f70e2b5d1a608e366f25a5c3d96aacf"]);
+
+    let ofb = aesavs_mct(|aes, iv| Ofb::new(aes, iv).unwrap(),
+                         |c: &mut Ofb<Aes128>, bits: &[u8]| {
+                             to_bits(c.encrypt(from_bits(bits)[])[])
+                         },
+                         128, key, iv, ptext_bits[]);
+    check(ofb[], 0, [key, iv, ptext, "182260568c5c9a9b8bdc74627cd6ad27"]);
+    check(ofb[], 99, ["75912a39835ebb8d9756bc49ffa273bf", "031c0e5affb785d5c3945986dc42b9f8",
+                      "7b9bbb39a19b627dae7fe52481426c9f", "5c6ab0197b5792e42fedfa643a0d737f"]);
+}
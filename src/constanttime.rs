@@ -21,7 +21,7 @@ impl ConstantTimeEq for u8 {
 
 impl<'a> ConstantTimeEq for &'a [u8] {
     fn const_time_eq_(&self, other: & &'a [u8]) -> int {
-        if self.len() == other.len() {
+        if self.len() != other.len() {
             0
         } else {
             self.iter().zip(other.iter())
@@ -30,3 +30,24 @@ impl<'a> ConstantTimeEq for &'a [u8] {
         }
     }
 }
+
+#[test]
+fn test_const_time_eq() {
+    let a: &[u8] = b"attack at dawn";
+    let same: &[u8] = b"attack at dawn";
+    let first: &[u8] = b"Attack at dawn";
+    let last: &[u8] = b"attack at dawN";
+    let shorter: &[u8] = b"attack at daw";
+    let empty: &[u8] = &[];
+
+    assert!(a.const_time_eq(&same));
+    assert!(empty.const_time_eq(&empty));
+    assert!(!a.const_time_eq(&first));
+    assert!(!a.const_time_eq(&last));
+    assert!(!a.const_time_eq(&shorter));
+    assert!(!shorter.const_time_eq(&a));
+    assert!(!a.const_time_eq(&empty));
+
+    assert!(0x5au8.const_time_eq(&0x5a));
+    assert!(!0x5au8.const_time_eq(&0x5b));
+}
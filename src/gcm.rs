@@ -0,0 +1,392 @@
+use aes::{Aes, AesKeySize};
+use cipher::{AeadError, BlockCipher, Ctr, StreamCipher};
+use constanttime::ConstantTimeEq;
+
+use std::slice::bytes;
+
+pub const BLOCK_LEN: uint = 16;
+pub const NONCE_LEN: uint = 12;
+pub const TAG_LEN: uint = 16;
+/// The most plaintext SP 800-38D allows under one IV, 2^39 - 256 bits.
+pub const MAX_PTEXT_LEN: u64 = (1 << 36) - 32;
+
+/// Multiplies `x` by `h` in GCM's GF(2^128), whose bits run from the most
+/// significant bit of `x[0]` to the least significant of `x[1]`.
+///
+/// This goes a bit at a time, and every step does the same work whatever
+/// the bits are: there are no branches on them and no tables indexed by
+/// them, so the time taken says nothing about `h` or the data.
+fn gf_mul(x: [u64, ..2], h: [u64, ..2]) -> [u64, ..2] {
+    let mut z = [0u64, 0];
+    let mut v = h;
+    for i in range(0u, 128) {
+        let bit = (x[i / 64] >> (63 - i % 64)) & 1;
+        let mask = -(bit as i64) as u64;
+        z[0] ^= v[0] & mask;
+        z[1] ^= v[1] & mask;
+
+        // v *= x, reducing by x^128 + x^7 + x^2 + x + 1.
+        let carry = -((v[1] & 1) as i64) as u64;
+        v[1] = (v[1] >> 1) | (v[0] << 63);
+        v[0] = (v[0] >> 1) ^ (0xe100000000000000 & carry);
+    }
+    z
+}
+
+/// The GHASH universal hash, keyed by the encrypted zero block.
+struct GHash {
+    h: [u64, ..2],
+    y: [u64, ..2],
+}
+
+impl GHash {
+    fn new(h: [u64, ..2]) -> GHash {
+        GHash { h: h, y: [0, 0] }
+    }
+
+    /// Hashes `data`, padded with zeros to a whole number of blocks.
+    fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(BLOCK_LEN) {
+            let mut block = [0u8, ..BLOCK_LEN];
+            bytes::copy_memory(&mut block, chunk);
+            self.y[0] ^= get_64h!(block, 0);
+            self.y[1] ^= get_64h!(block, 1);
+            self.y = gf_mul(self.y, self.h);
+        }
+    }
+
+    /// Hashes the block of bit lengths that ends every GHASH input.
+    fn finalize(mut self, len_a: uint, len_b: uint) -> [u8, ..BLOCK_LEN] {
+        let mut lens = [0u8, ..BLOCK_LEN];
+        store_64h!(len_a as u64 * 8, lens[mut ..8]);
+        store_64h!(len_b as u64 * 8, lens[mut 8..]);
+        self.update_padded(&lens);
+
+        let mut out = [0u8, ..BLOCK_LEN];
+        store_64h!(self.y[0], out[mut ..8]);
+        store_64h!(self.y[1], out[mut 8..]);
+        out
+    }
+}
+
+impl Drop for GHash {
+    #[inline(never)]
+    fn drop(&mut self) {
+        for w in self.h.iter_mut().chain(self.y.iter_mut()) {
+            *w = 0;
+        }
+    }
+}
+
+/// AES-GCM (SP 800-38D). Sealed messages are the ciphertext followed by
+/// the tag, which is 16 bytes unless `with_tag_len` says otherwise.
+///
+/// IVs can be any non-zero length, but 12-byte ones are used as they are
+/// and are the ones to prefer; others are hashed into a counter block
+/// first. An IV must never be reused with the same key.
+pub struct AesGcm<KS> {
+    aes: Aes<KS>,
+    /// The GHASH key, the encrypted zero block.
+    h: [u64, ..2],
+    tag_len: uint,
+}
+
+impl<KS: AesKeySize> AesGcm<KS> {
+    /// Returns None if the key is the wrong length for `KS`.
+    pub fn new(key: &[u8]) -> Option<AesGcm<KS>> {
+        AesGcm::with_tag_len(key, TAG_LEN)
+    }
+
+    /// Like `new`, but with tags truncated to `tag_len` bytes. SP 800-38D
+    /// allows 12 to 16 bytes, and 4 or 8 for protocols that limit how much
+    /// is sealed under one key; anything else gives None.
+    pub fn with_tag_len(key: &[u8], tag_len: uint) -> Option<AesGcm<KS>> {
+        if tag_len != 4 && tag_len != 8 && (tag_len < 12 || tag_len > TAG_LEN) {
+            return None;
+        }
+        let aes: Aes<KS> = match Aes::new(key) {
+            Some(aes) => aes,
+            None => return None,
+        };
+        let mut block = [0u8, ..BLOCK_LEN];
+        aes.encrypt_block(&[0u8, ..BLOCK_LEN], &mut block);
+        let h = [get_64h!(block, 0), get_64h!(block, 1)];
+        for b in block.iter_mut() {
+            *b = 0;
+        }
+        Some(AesGcm { aes: aes, h: h, tag_len: tag_len })
+    }
+
+    /// Encrypts `ptext` and authenticates it along with `aad`. Returns None
+    /// if `iv` is empty or `ptext` is longer than `MAX_PTEXT_LEN`.
+    pub fn seal(&self, iv: &[u8], aad: &[u8], ptext: &[u8]) -> Option<Vec<u8>> {
+        if too_long(ptext.len()) {
+            return None;
+        }
+        let (mut ctr, j0) = match self.setup(iv) {
+            Some(s) => s,
+            None => return None,
+        };
+        let mut out = ctr.encrypt(ptext);
+        let tag = self.tag(j0[], aad, out[]);
+        out.push_all(tag[]);
+        Some(out)
+    }
+
+    /// Checks the tag and decrypts. Nothing is decrypted unless the tag
+    /// matches.
+    pub fn open(&self, iv: &[u8], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, AeadError> {
+        let (mut ctr, j0) = match self.setup(iv) {
+            Some(s) => s,
+            None => return Err(AeadError::InvalidLength),
+        };
+        if sealed.len() < self.tag_len {
+            return Err(AeadError::TagMismatch);
+        }
+        let split = sealed.len() - self.tag_len;
+        let (ctext, tag) = (sealed[..split], sealed[split..]);
+        if too_long(ctext.len()) {
+            return Err(AeadError::InvalidLength);
+        }
+
+        if !self.tag(j0[], aad, ctext)[].const_time_eq(&tag) {
+            return Err(AeadError::TagMismatch);
+        }
+        Ok(ctr.decrypt(ctext))
+    }
+
+    /// The pre-counter block J0, and the keystream that starts just after
+    /// it. Returns None if `iv` is empty.
+    fn setup(&self, iv: &[u8]) -> Option<(Ctr<&Aes<KS>>, [u8, ..BLOCK_LEN])> {
+        if iv.len() == 0 {
+            return None;
+        }
+        let mut j0 = [0u8, ..BLOCK_LEN];
+        if iv.len() == NONCE_LEN {
+            bytes::copy_memory(&mut j0, iv);
+            j0[BLOCK_LEN - 1] = 1;
+        } else {
+            let mut ghash = GHash::new(self.h);
+            ghash.update_padded(iv);
+            j0 = ghash.finalize(0, iv.len());
+        }
+
+        // The message uses GCM's 32-bit counter from J0 + 1.
+        let mut ctr = Ctr::new(&self.aes, &j0, 4).unwrap();
+        ctr.seek(BLOCK_LEN as u64);
+        Some((ctr, j0))
+    }
+
+    fn tag(&self, j0: &[u8], aad: &[u8], ctext: &[u8]) -> Vec<u8> {
+        let mut ghash = GHash::new(self.h);
+        ghash.update_padded(aad);
+        ghash.update_padded(ctext);
+        let s = ghash.finalize(aad.len(), ctext.len());
+
+        let mut tag = Vec::from_elem(BLOCK_LEN, 0u8);
+        self.aes.encrypt_block(j0, tag[mut]);
+        for (t, &x) in tag.iter_mut().zip(s.iter()) {
+            *t ^= x;
+        }
+        tag.truncate(self.tag_len);
+        tag
+    }
+}
+
+/// Whether `len` bytes is more than one IV may encrypt. Past that the 32-bit
+/// block counter would wrap around onto J0.
+fn too_long(len: uint) -> bool {
+    len as u64 > MAX_PTEXT_LEN
+}
+
+#[unsafe_destructor]
+impl<KS> Drop for AesGcm<KS> {
+    #[inline(never)]
+    fn drop(&mut self) {
+        for w in self.h.iter_mut() {
+            *w = 0;
+        }
+    }
+}
+
+/// GMAC: GCM authenticating `data` with nothing to encrypt. Like GCM, it
+/// needs a fresh IV for every message.
+pub struct Gmac<KS> {
+    gcm: AesGcm<KS>,
+}
+
+impl<KS: AesKeySize> Gmac<KS> {
+    /// Returns None if the key is the wrong length for `KS`.
+    pub fn new(key: &[u8]) -> Option<Gmac<KS>> {
+        AesGcm::new(key).map(|gcm| Gmac { gcm: gcm })
+    }
+
+    /// Like `AesGcm::with_tag_len`.
+    pub fn with_tag_len(key: &[u8], tag_len: uint) -> Option<Gmac<KS>> {
+        AesGcm::with_tag_len(key, tag_len).map(|gcm| Gmac { gcm: gcm })
+    }
+
+    /// Returns None if `iv` is empty.
+    pub fn mac(&self, iv: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+        self.gcm.seal(iv, data, &[])
+    }
+
+    /// Checks `tag` in constant time. An empty `iv` never verifies.
+    pub fn verify(&self, iv: &[u8], data: &[u8], tag: &[u8]) -> bool {
+        self.gcm.open(iv, data, tag) == Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+static KEY: &'static str = "feffe9928665731c6d6a8f9467308308";
+#[cfg(test)]
+static AAD: &'static str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+#[cfg(test)]
+static PTEXT: &'static str =
+    "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+     1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+
+#[test]
+fn test_gf_mul() {
+    // H and X1 from test case 2 of the GCM specification.
+    let h = [0x66e94bd4ef8a2c3b, 0x884cfa59ca342b2e];
+    let c = [0x0388dace60b6a392, 0xf328c2b971b2fe78];
+    assert_eq!(gf_mul(c, h)[], [0x5e2ec74691706288u64, 0x2c85b0685353deb7][]);
+    assert_eq!(gf_mul(h, c)[], gf_mul(c, h)[]);
+
+    // The first bit is 1.
+    let one = [1u64 << 63, 0];
+    assert_eq!(gf_mul(one, h)[], h[]);
+    assert_eq!(gf_mul([0, 0], h)[], [0u64, 0][]);
+}
+
+#[test]
+fn test_aes128_gcm() {
+    use cipher::KeySize128;
+    use serialize::hex::{FromHex, ToHex};
+    // Test cases 1 to 6 from the GCM specification. Test case 3 is the
+    // 64-byte plaintext that the others cut short, without AAD.
+    let zero = Vec::from_elem(16, 0u8);
+    let gcm: AesGcm<KeySize128> = AesGcm::new(zero[]).unwrap();
+    assert_eq!(gcm.seal(zero[..12], &[], &[]).unwrap()[].to_hex()[],
+               "58e2fccefa7e3061367f1d57a4e7455a");
+    assert_eq!(gcm.seal(zero[..12], &[], zero[]).unwrap()[].to_hex()[],
+               "0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf");
+
+    let gcm: AesGcm<KeySize128> = AesGcm::new(KEY.from_hex().unwrap()[]).unwrap();
+    let aad = AAD.from_hex().unwrap();
+    let ptext = PTEXT.from_hex().unwrap();
+
+    let iv = "cafebabefacedbaddecaf888".from_hex().unwrap();
+    let full = format!("{}1aafd255", PTEXT).from_hex().unwrap();
+    let sealed = gcm.seal(iv[], &[], full[]).unwrap();
+    assert_eq!(sealed[].to_hex()[],
+               "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+                21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985\
+                4d5c2af327cd64a62cf35abd2ba6fab4");
+    assert_eq!(gcm.open(iv[], &[], sealed[]), Ok(full));
+
+    let cases = [
+        ("cafebabefacedbaddecaf888",
+         "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+          21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091\
+          5bc94fbc3221a5db94fae95ae7121a47"),
+        ("cafebabefacedbad",
+         "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c7423\
+          73806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598\
+          3612d2e79e3b0785561be14aaca2fccb"),
+        ("9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+          c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+         "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca7\
+          01e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5\
+          619cc5aefffe0bfa462af43c1699d050"),
+    ];
+    for &(iv, expected) in cases.iter() {
+        let iv = iv.from_hex().unwrap();
+        let sealed = gcm.seal(iv[], aad[], ptext[]).unwrap();
+        assert_eq!(sealed[].to_hex()[], expected);
+        assert_eq!(gcm.open(iv[], aad[], sealed[]), Ok(ptext.clone()));
+    }
+}
+
+#[test]
+fn test_aes256_gcm() {
+    use cipher::KeySize256;
+    use serialize::hex::{FromHex, ToHex};
+    // Test case 16 from the GCM specification.
+    let key = "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308"
+        .from_hex().unwrap();
+    let gcm: AesGcm<KeySize256> = AesGcm::new(key[]).unwrap();
+    let iv = "cafebabefacedbaddecaf888".from_hex().unwrap();
+    assert_eq!(gcm.seal(iv[], AAD.from_hex().unwrap()[], PTEXT.from_hex().unwrap()[])
+                   .unwrap()[].to_hex()[],
+               "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+                8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662\
+                76fc6ece0f4e1768cddf8853bb2d551b");
+
+    assert!(AesGcm::<KeySize256>::new(KEY.from_hex().unwrap()[]).is_none());
+}
+
+#[test]
+fn test_gcm_tampering_and_truncation() {
+    use cipher::KeySize128;
+    use serialize::hex::FromHex;
+    let key = KEY.from_hex().unwrap();
+    let iv = "cafebabefacedbaddecaf888".from_hex().unwrap();
+    let gcm: AesGcm<KeySize128> = AesGcm::new(key[]).unwrap();
+    let sealed = gcm.seal(iv[], b"header", b"attack at dawn").unwrap();
+
+    for i in range(0, sealed.len()) {
+        let mut bad = sealed.clone();
+        bad[i] ^= 0x80;
+        assert_eq!(gcm.open(iv[], b"header", bad[]), Err(AeadError::TagMismatch));
+    }
+    assert_eq!(gcm.open(iv[], b"footer", sealed[]), Err(AeadError::TagMismatch));
+    assert_eq!(gcm.open(iv[..8], b"header", sealed[]), Err(AeadError::TagMismatch));
+    assert_eq!(gcm.open(iv[], b"header", sealed[..TAG_LEN - 1]),
+               Err(AeadError::TagMismatch));
+
+    assert!(gcm.seal(&[], b"header", b"attack at dawn").is_none());
+    assert_eq!(gcm.open(&[], b"header", sealed[]), Err(AeadError::InvalidLength));
+
+    // Too long to build here, so check the limit itself: 2^32 - 2 blocks.
+    if cfg!(target_word_size = "64") {
+        assert!(!too_long((((1u64 << 32) - 2) * BLOCK_LEN as u64) as uint));
+        assert!(too_long(MAX_PTEXT_LEN as uint + 1));
+    }
+
+    // A truncated tag is a prefix of the full one.
+    for &tag_len in [4u, 8, 12, 13, 14, 15].iter() {
+        let short: AesGcm<KeySize128> = AesGcm::with_tag_len(key[], tag_len).unwrap();
+        let sealed_short = short.seal(iv[], b"header", b"attack at dawn").unwrap();
+        assert_eq!(sealed_short[], sealed[..sealed.len() - TAG_LEN + tag_len]);
+        assert_eq!(short.open(iv[], b"header", sealed_short[]).unwrap()[], b"attack at dawn");
+        assert_eq!(short.open(iv[], b"header", sealed[]), Err(AeadError::TagMismatch));
+    }
+    for &tag_len in [0u, 3, 10, 11, 17].iter() {
+        assert!(AesGcm::<KeySize128>::with_tag_len(key[], tag_len).is_none());
+    }
+}
+
+#[test]
+fn test_gmac() {
+    use cipher::KeySize128;
+    use serialize::hex::{FromHex, ToHex};
+    // From the SP 800-38D validation vectors (gcmEncryptExtIV128).
+    let key = "77be63708971c4e240d1cb79e8d77feb".from_hex().unwrap();
+    let iv = "e0e00f19fed7ba0136a797f3".from_hex().unwrap();
+    let data = "7a43ec1d9c0a5a78a0b16533a6213cab".from_hex().unwrap();
+    let gmac: Gmac<KeySize128> = Gmac::new(key[]).unwrap();
+    let tag = gmac.mac(iv[], data[]).unwrap();
+    assert_eq!(tag[].to_hex()[], "209fcc8d3675ed938e9c7166709dd946");
+    assert!(gmac.verify(iv[], data[], tag[]));
+    assert!(!gmac.verify(iv[], data[..15], tag[]));
+    assert!(!gmac.verify(iv[], data[], tag[..8]));
+    assert!(gmac.mac(&[], data[]).is_none());
+    assert!(!gmac.verify(&[], data[], tag[]));
+
+    let key = "11754cd72aec309bf52f7687212e8957".from_hex().unwrap();
+    let iv = "3c819d9a9bed087615030b65".from_hex().unwrap();
+    let gmac: Gmac<KeySize128> = Gmac::with_tag_len(key[], 8).unwrap();
+    assert_eq!(gmac.mac(iv[], &[]).unwrap()[].to_hex()[], "250327c674aaf477");
+}
@@ -0,0 +1,137 @@
+use {HashFn, hash_output_size};
+use hmac::Hmac;
+use securemem::SecureMem;
+
+use std::cmp::min;
+
+/// The HKDF-Extract step of RFC 5869: condenses the input keying material
+/// into a pseudorandom key as long as the hash output. An empty salt is the
+/// same as the all-zero salt the RFC asks for, since HMAC zero-pads the key
+/// anyway.
+pub fn extract<H: HashFn>(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    let mut hmac: Hmac<H> = Hmac::new(salt);
+    hmac.update(ikm);
+    hmac.finalize()
+}
+
+/// The HKDF-Expand step of RFC 5869: stretches `prk` into `len` bytes of
+/// output keying material bound to `info`. Returns `None` if `prk` is
+/// shorter than the hash output, as RFC 5869 requires it to be at least
+/// that long, or if `len` is more than 255 times the hash output size.
+pub fn expand<H: HashFn>(prk: &[u8], info: &[u8], len: uint) -> Option<Vec<u8>> {
+    let hash_len = hash_output_size::<H>();
+    if prk.len() < hash_len || len > 255 * hash_len {
+        return None;
+    }
+
+    let mut okm = Vec::with_capacity(len);
+    let mut t = SecureMem::new(Vec::new());
+
+    for i in range(1u, (len + hash_len - 1) / hash_len + 1) {
+        let mut hmac: Hmac<H> = Hmac::new(prk);
+        hmac.update(t.as_slice());
+        hmac.update(info);
+        hmac.update(&[i as u8]);
+        t = SecureMem::new(hmac.finalize());
+
+        let n = min(len - okm.len(), hash_len);
+        okm.push_all(t.slice_to(n));
+    }
+
+    Some(okm)
+}
+
+/// Extract-then-expand in one go. The intermediate pseudorandom key is
+/// wiped before returning.
+pub fn hkdf<H: HashFn>(salt: &[u8], ikm: &[u8], info: &[u8], len: uint) -> Option<Vec<u8>> {
+    let prk = SecureMem::new(extract::<H>(salt, ikm));
+    expand::<H>(prk.as_slice(), info, len)
+}
+
+#[cfg(test)]
+fn check_rfc5869<H: HashFn>(vectors: &[(&str, &str, &str, &str, &str)]) {
+    use serialize::hex::{FromHex, ToHex};
+    for &(ikm, salt, info, prk, okm) in vectors.iter() {
+        let (ikm, salt, info) = (ikm.from_hex().unwrap(), salt.from_hex().unwrap(),
+                                 info.from_hex().unwrap());
+        let len = okm.len() / 2;
+
+        let p = extract::<H>(salt[], ikm[]);
+        assert_eq!(p[].to_hex()[], prk);
+        assert_eq!(expand::<H>(p[], info[], len).unwrap()[].to_hex()[], okm);
+        assert_eq!(hkdf::<H>(salt[], ikm[], info[], len).unwrap()[].to_hex()[], okm);
+    }
+}
+
+#[test]
+fn test_hkdf_sha256_rfc5869() {
+    use sha2::Sha256;
+    // (IKM, salt, info, PRK, OKM): test cases 1-3
+    static VECTORS: &'static [(&'static str, &'static str, &'static str,
+                               &'static str, &'static str)] = &[
+        ("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+         "000102030405060708090a0b0c",
+         "f0f1f2f3f4f5f6f7f8f9",
+         "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5",
+         "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"),
+        ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f404142434445464748494a4b4c4d4e4f",
+         "606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9fa0a1a2a3a4a5a6a7a8a9aaabacadaeaf",
+         "b0b1b2b3b4b5b6b7b8b9babbbcbdbebfc0c1c2c3c4c5c6c7c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedfe0e1e2e3e4e5e6e7e8e9eaebecedeeeff0f1f2f3f4f5f6f7f8f9fafbfcfdfeff",
+         "06a6b88c5853361a06104c9ceb35b45cef760014904671014a193f40c15fc244",
+         "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71cc30c58179ec3e87c14c01d5c1f3434f1d87"),
+        ("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+         "",
+         "",
+         "19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04",
+         "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8"),
+    ];
+    check_rfc5869::<Sha256>(VECTORS);
+}
+
+#[test]
+fn test_hkdf_sha1_rfc5869() {
+    use sha1::Sha1;
+    // (IKM, salt, info, PRK, OKM): test cases 4-7. Case 7's absent salt is
+    // passed as an empty one.
+    static VECTORS: &'static [(&'static str, &'static str, &'static str,
+                               &'static str, &'static str)] = &[
+        ("0b0b0b0b0b0b0b0b0b0b0b",
+         "000102030405060708090a0b0c",
+         "f0f1f2f3f4f5f6f7f8f9",
+         "9b6c18c432a7bf8f0e71c8eb88f4b30baa2ba243",
+         "085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896"),
+        ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f404142434445464748494a4b4c4d4e4f",
+         "606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9fa0a1a2a3a4a5a6a7a8a9aaabacadaeaf",
+         "b0b1b2b3b4b5b6b7b8b9babbbcbdbebfc0c1c2c3c4c5c6c7c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedfe0e1e2e3e4e5e6e7e8e9eaebecedeeeff0f1f2f3f4f5f6f7f8f9fafbfcfdfeff",
+         "8adae09a2a307059478d309b26c4115a224cfaf6",
+         "0bd770a74d1160f7c9f12cd5912a06ebff6adcae899d92191fe4305673ba2ffe8fa3f1a4e5ad79f3f334b3b202b2173c486ea37ce3d397ed034c7f9dfeb15c5e927336d0441f4c4300e2cff0d0900b52d3b4"),
+        ("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+         "",
+         "",
+         "da8c8a73c7fa77288ec6f5e7c297786aa0d32d01",
+         "0ac1af7002b3d761d1e55298da9d0506b9ae52057220a306e07b6b87e8df21d0ea00033de03984d34918"),
+        ("0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c",
+         "",
+         "",
+         "2adccada18779e7c2077ad2eb19d3f3e731385dd",
+         "2c91117204d745f3500d636a62f64f0ab3bae548aa53d423b0d1f27ebba6f5e5673a081d70cce7acfc48"),
+    ];
+    check_rfc5869::<Sha1>(VECTORS);
+}
+
+#[test]
+fn test_hkdf_expand_limit() {
+    use sha2::Sha256;
+    let prk = extract::<Sha256>(b"salt", b"ikm");
+    assert_eq!(expand::<Sha256>(prk[], b"", 255 * 32).unwrap().len(), 255 * 32);
+    assert!(expand::<Sha256>(prk[], b"", 255 * 32 + 1).is_none());
+}
+
+#[test]
+fn test_hkdf_short_prk() {
+    use sha2::Sha256;
+    let prk = extract::<Sha256>(b"salt", b"ikm");
+    assert!(expand::<Sha256>(prk[..31], b"", 32).is_none());
+    assert!(expand::<Sha256>(&[], b"", 32).is_none());
+    assert!(expand::<Sha256>(prk[], b"", 32).is_some());
+}
@@ -0,0 +1,225 @@
+use {HashFn, hash_block_size};
+use constanttime::ConstantTimeEq;
+use securemem::SecureMem;
+
+/// HMAC (RFC 2104), generic over any `HashFn`.
+#[deriving(Clone)]
+pub struct Hmac<H> {
+    inner: H,
+    outer: H,
+}
+
+impl<H: HashFn> Hmac<H> {
+    /// Keys longer than the hash's block size are hashed first, as the RFC
+    /// requires.
+    pub fn new(key: &[u8]) -> Hmac<H> {
+        let bs = hash_block_size::<H>();
+        let mut k = SecureMem::new(Vec::from_elem(bs, 0u8));
+
+        if key.len() > bs {
+            let mut h: H = HashFn::create();
+            h.update(key);
+            let hk = SecureMem::new(h.digest());
+            for (d, &s) in k.iter_mut().zip(hk.iter()) {
+                *d = s;
+            }
+        } else {
+            for (d, &s) in k.iter_mut().zip(key.iter()) {
+                *d = s;
+            }
+        }
+
+        let mut pad = SecureMem::new(Vec::from_elem(bs, 0u8));
+        let mut inner: H = HashFn::create();
+        let mut outer: H = HashFn::create();
+
+        for (p, &b) in pad.iter_mut().zip(k.iter()) {
+            *p = b ^ 0x36;
+        }
+        inner.update(pad.as_slice());
+
+        for (p, &b) in pad.iter_mut().zip(k.iter()) {
+            *p = b ^ 0x5c;
+        }
+        outer.update(pad.as_slice());
+
+        Hmac { inner: inner, outer: outer }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        let Hmac { inner, mut outer } = self;
+        outer.update(inner.digest()[]);
+        outer.digest()
+    }
+
+    /// Checks `tag` against the MAC in constant time. The tag has to be the
+    /// full length of the digest; truncated tags aren't accepted.
+    pub fn verify(self, tag: &[u8]) -> bool {
+        let mac = self.finalize();
+        mac[].const_time_eq(&tag)
+    }
+}
+
+#[cfg(test)]
+fn hex_hmac<H: HashFn>(key: &str, data: &str) -> String {
+    use serialize::hex::{FromHex, ToHex};
+    let mut hmac: Hmac<H> = Hmac::new(key.from_hex().unwrap()[]);
+    hmac.update(data.from_hex().unwrap()[]);
+    hmac.finalize()[].to_hex()
+}
+
+#[test]
+fn test_hmac_md5_rfc2202() {
+    use md5::Md5;
+    static VECTORS: &'static [(&'static str, &'static str, &'static str)] = &[
+        ("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+         "4869205468657265",
+         "9294727a3638bb1c13f48ef8158bfc9d"),
+        ("4a656665",
+         "7768617420646f2079612077616e7420666f72206e6f7468696e673f",
+         "750c783e6ab0b503eaa86e310a5db738"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+         "dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd",
+         "56be34521d144c88dbb8c733f0e8b3f6"),
+        ("0102030405060708090a0b0c0d0e0f10111213141516171819",
+         "cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd",
+         "697eaf0aca3a3aea3a75164746ffaa79"),
+        ("0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c",
+         "546573742057697468205472756e636174696f6e",
+         "56461ef2342edc00f9bab995690efd4c"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+         "54657374205573696e67204c6172676572205468616e20426c6f636b2d53697a65204b6579202d2048617368204b6579204669727374",
+         "6b1ab7fe4bd7bf8f0b62e6ce61b9d0cd"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+         "54657374205573696e67204c6172676572205468616e20426c6f636b2d53697a65204b657920616e64204c6172676572205468616e204f6e6520426c6f636b2d53697a652044617461",
+         "6f630fad67cda0ee1fb1f562db3aa53e"),
+    ];
+
+    for &(key, data, mac) in VECTORS.iter() {
+        assert_eq!(hex_hmac::<Md5>(key, data)[], mac);
+    }
+}
+
+#[test]
+fn test_hmac_sha1_rfc2202() {
+    use sha1::Sha1;
+    static VECTORS: &'static [(&'static str, &'static str, &'static str)] = &[
+        ("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+         "4869205468657265",
+         "b617318655057264e28bc0b6fb378c8ef146be00"),
+        ("4a656665",
+         "7768617420646f2079612077616e7420666f72206e6f7468696e673f",
+         "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+         "dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd",
+         "125d7342b9ac11cd91a39af48aa17b4f63f175d3"),
+        ("0102030405060708090a0b0c0d0e0f10111213141516171819",
+         "cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd",
+         "4c9007f4026250c6bc8414f9bf50c86c2d7235da"),
+        ("0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c",
+         "546573742057697468205472756e636174696f6e",
+         "4c1a03424b55e07fe7f27be1d58bb9324a9a5a04"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+         "54657374205573696e67204c6172676572205468616e20426c6f636b2d53697a65204b6579202d2048617368204b6579204669727374",
+         "aa4ae5e15272d00e95705637ce8a3b55ed402112"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+         "54657374205573696e67204c6172676572205468616e20426c6f636b2d53697a65204b657920616e64204c6172676572205468616e204f6e6520426c6f636b2d53697a652044617461",
+         "e8e99d0f45237d786d6bbaa7965c7808bbff1a91"),
+    ];
+
+    for &(key, data, mac) in VECTORS.iter() {
+        assert_eq!(hex_hmac::<Sha1>(key, data)[], mac);
+    }
+}
+
+#[test]
+fn test_hmac_sha2_rfc4231() {
+    use sha2::{Sha224, Sha256, Sha384, Sha512};
+    // (key, data, HMAC-SHA-224, HMAC-SHA-256, HMAC-SHA-384, HMAC-SHA-512)
+    static VECTORS: &'static [(&'static str, &'static str, &'static str,
+                               &'static str, &'static str, &'static str)] = &[
+        ("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+         "4869205468657265",
+         "896fb1128abbdf196832107cd49df33f47b4b1169912ba4f53684b22",
+         "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+         "afd03944d84895626b0825f4ab46907f15f9dadbe4101ec682aa034c7cebc59cfaea9ea9076ede7f4af152e8b2fa9cb6",
+         "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854"),
+        ("4a656665",
+         "7768617420646f2079612077616e7420666f72206e6f7468696e673f",
+         "a30e01098bc6dbbf45690f3a7e9e6d0f8bbea2a39e6148008fd05e44",
+         "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+         "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649",
+         "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+         "dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd",
+         "7fb3cb3588c6c1f6ffa9694d7d6ad2649365b0c1f65d69d1ec8333ea",
+         "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+         "88062608d3e6ad8a0aa2ace014c8a86f0aa635d947ac9febe83ef4e55966144b2a5ab39dc13814b94e3ab6e101a34f27",
+         "fa73b0089d56a284efb0f0756c890be9b1b5dbdd8ee81a3655f83e33b2279d39bf3e848279a722c806b485a47e67c807b946a337bee8942674278859e13292fb"),
+        ("0102030405060708090a0b0c0d0e0f10111213141516171819",
+         "cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd",
+         "6c11506874013cac6a2abc1bb382627cec6a90d86efc012de7afec5a",
+         "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+         "3e8a69b7783c25851933ab6290af6ca77a9981480850009cc5577c6e1f573b4e6801dd23c4a7d679ccf8a386c674cffb",
+         "b0ba465637458c6990e5a8c5f61d4af7e576d97ff94b872de76f8050361ee3dba91ca5c11aa25eb4d679275cc5788063a5f19741120c4f2de2adebeb10a298dd"),
+        ("0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c",
+         "546573742057697468205472756e636174696f6e",
+         "0e2aea68a90c8d37c988bcdb9fca6fa8099cd857c7ec4a1815cac54c",
+         "a3b6167473100ee06e0c796c2955552bfa6f7c0a6a8aef8b93f860aab0cd20c5",
+         "3abf34c3503b2a23a46efc619baef897f4c8e42c934ce55ccbae9740fcbc1af4ca62269e2a37cd88ba926341efe4aeea",
+         "415fad6271580a531d4179bc891d87a650188707922a4fbb36663a1eb16da008711c5b50ddd0fc235084eb9d3364a1454fb2ef67cd1d29fe6773068ea266e96b"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+         "54657374205573696e67204c6172676572205468616e20426c6f636b2d53697a65204b6579202d2048617368204b6579204669727374",
+         "95e9a0db962095adaebe9b2d6f0dbce2d499f112f2d2b7273fa6870e",
+         "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+         "4ece084485813e9088d2c63a041bc5b44f9ef1012a2b588f3cd11f05033ac4c60c2ef6ab4030fe8296248df163f44952",
+         "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598"),
+        ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+         "5468697320697320612074657374207573696e672061206c6172676572207468616e20626c6f636b2d73697a65206b657920616e642061206c6172676572207468616e20626c6f636b2d73697a6520646174612e20546865206b6579206e6565647320746f20626520686173686564206265666f7265206265696e6720757365642062792074686520484d414320616c676f726974686d2e",
+         "3a854166ac5d9f023f54d517d0b39dbd946770db9c2b95c9f6f565d1",
+         "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+         "6617178e941f020d351e2f254e8fd32c602420feb0b8fb9adccebb82461e99c5a678cc31e799176d3860e6110c46523e",
+         "e37b6a775dc87dbaa4dfa9f96e5e3ffddebd71f8867289865df5a32d20cdc944b6022cac3c4982b10d5eeb55c3e4de15134676fb6de0446065c97440fa8c6a58"),
+    ];
+
+    for (i, &(key, data, m224, m256, m384, m512)) in VECTORS.iter().enumerate() {
+        // Test case 5 only specifies the output truncated to 128 bits.
+        let check = |mac: String, expected: &str| {
+            if i == 4 {
+                assert_eq!(mac[..32], expected[..32]);
+            } else {
+                assert_eq!(mac[], expected);
+            }
+        };
+        check(hex_hmac::<Sha224>(key, data), m224);
+        check(hex_hmac::<Sha256>(key, data), m256);
+        check(hex_hmac::<Sha384>(key, data), m384);
+        check(hex_hmac::<Sha512>(key, data), m512);
+    }
+}
+
+#[test]
+fn test_hmac_verify() {
+    use serialize::hex::FromHex;
+    use sha2::Sha256;
+    let tag = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        .from_hex().unwrap();
+
+    let mut hmac: Hmac<Sha256> = Hmac::new(b"Jefe");
+    hmac.update(b"what do ya want for nothing?");
+    assert!(hmac.verify(tag[]));
+
+    let mut bad = tag.clone();
+    bad[31] ^= 1;
+    let mut hmac: Hmac<Sha256> = Hmac::new(b"Jefe");
+    hmac.update(b"what do ya want for nothing?");
+    assert!(!hmac.verify(bad[]));
+
+    let mut hmac: Hmac<Sha256> = Hmac::new(b"Jefe");
+    hmac.update(b"what do ya want for nothing?");
+    assert!(!hmac.verify(tag[..16]));
+}
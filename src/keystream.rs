@@ -0,0 +1,249 @@
+use std::cmp::min;
+use std::ptr::copy_nonoverlapping_memory;
+use std::simd::u32x4;
+
+/// Permutes a single state.
+pub type Permute = fn(&mut [u32, ..16], uint);
+
+/// Permutes four states at once. Each vector holds one state word across
+/// four consecutive blocks, so the rounds need no shuffles between lanes.
+pub type Permute4 = fn(&mut [u32x4, ..16], uint);
+
+/// Permutes eight states at once, laid out as for `Permute4`.
+pub type Permute8 = fn(&mut [[u32, ..8], ..16], uint);
+
+/// How many blocks `refill` makes at a time, and the permutation for them.
+pub enum Lanes {
+    One,
+    Four(Permute4),
+    Eight(Permute8),
+}
+
+/// Enough room for the most blocks a multi-block path makes at a time.
+const BUF_LEN: uint = 8 * 64;
+
+/// The block-counter machinery shared by the ChaCha and Salsa20 families:
+/// the state is permuted, added back to itself, and used as 64 bytes of
+/// keystream; then the counter moves on. The families only differ in the
+/// state layout and the permutation.
+pub struct Keystream {
+    state: [u32, ..16],
+    /// The keystream for the `buf_len / 64` blocks just before the counter.
+    buf: [u8, ..BUF_LEN],
+    buf_len: uint,
+    index: uint,
+    /// Where the counter's low word is. The high word, if any, follows it.
+    counter: uint,
+    /// Whether the counter is a single word, as in the RFC 8439 layout.
+    narrow: bool,
+    /// Set once a single-word counter has wrapped; going on would repeat
+    /// keystream.
+    exhausted: bool,
+    permute: Permute,
+    /// The multi-block path, chosen once when the stream is made.
+    lanes: Lanes,
+    double_rounds: uint,
+}
+
+impl Keystream {
+    /// `state` already holds the initial counter at word `counter`.
+    pub fn new(state: [u32, ..16], counter: uint, narrow: bool, permute: Permute,
+               lanes: Lanes, double_rounds: uint) -> Keystream {
+        Keystream {
+            state: state,
+            buf: [0, ..BUF_LEN],
+            buf_len: 0,
+            index: 0,
+            counter: counter,
+            narrow: narrow,
+            exhausted: false,
+            permute: permute,
+            lanes: lanes,
+            double_rounds: double_rounds,
+        }
+    }
+
+    /// Moves to `offset` bytes into the keystream, counting from block 0.
+    ///
+    /// Panics if a single-word counter can't reach `offset`.
+    pub fn seek(&mut self, offset: u64) {
+        let block = offset / 64;
+        if self.narrow {
+            assert!(block <= 0xffffffff, "seek past the end of the keystream");
+        } else {
+            self.state[self.counter + 1] = (block >> 32) as u32;
+        }
+        self.state[self.counter] = block as u32;
+        self.exhausted = false;
+
+        self.buf_len = 0;
+        self.index = 0;
+        if offset % 64 != 0 {
+            self.refill();
+            self.index = (offset % 64) as uint;
+        }
+    }
+
+    /// The offset of the next keystream byte, as `seek` takes it.
+    pub fn current_position(&self) -> u64 {
+        (self.next_block() * 64 - self.buf_len as u64) + self.index as u64
+    }
+
+    pub fn combine(&mut self, input: &[u8], output: &mut [u8]) {
+        let len = min(input.len(), output.len());
+        let mut pos = 0;
+        while pos < len {
+            // Blocks are only made once they're needed, so using up the last
+            // block of a single-word counter isn't an error.
+            if self.index == self.buf_len {
+                self.refill();
+            }
+            let n = min(self.buf_len - self.index, len - pos);
+            xor_into(input[pos..pos + n], self.buf[self.index..self.index + n],
+                     output[mut pos..pos + n]);
+            self.index += n;
+            pos += n;
+        }
+    }
+
+    fn next_block(&self) -> u64 {
+        if self.exhausted {
+            1 << 32
+        } else if self.narrow {
+            self.state[self.counter] as u64
+        } else {
+            (self.state[self.counter + 1] as u64 << 32) | self.state[self.counter] as u64
+        }
+    }
+
+    fn refill(&mut self) {
+        assert!(!self.exhausted, "keystream exhausted");
+        let lanes = self.lanes;
+        match lanes {
+            Lanes::Eight(permute8) if self.has_room(8) => self.run_eight_blocks(permute8),
+            Lanes::Four(permute4) if self.has_room(4) => self.run_four_blocks(permute4),
+            _ => self.run_one_block(),
+        }
+        self.index = 0;
+    }
+
+    /// Whether `blocks` more blocks fit. A single-word counter mustn't be
+    /// carried past its last block.
+    fn has_room(&self, blocks: u64) -> bool {
+        !self.narrow || self.next_block() + blocks <= 1 << 32
+    }
+
+    fn run_one_block(&mut self) {
+        let mut state = self.state;
+        (self.permute)(&mut state, self.double_rounds);
+        for i in range(0u, 16) {
+            state[i] += self.state[i];
+        }
+        // The keystream is the words in little-endian order, whatever the
+        // host's order is.
+        for i in range(0u, 16) {
+            store_32l!(state[i], self.buf[mut 4*i..]);
+        }
+        self.buf_len = 64;
+        self.bump_counter(1);
+    }
+
+    fn run_four_blocks(&mut self, permute4: Permute4) {
+        let mut x = [u32x4(0, 0, 0, 0), ..16];
+        for i in range(0u, 16) {
+            let w = self.state[i];
+            x[i] = u32x4(w, w, w, w);
+        }
+        let ctr = self.next_block();
+        let lo = |j: u64| (ctr + j) as u32;
+        let hi = |j: u64| ((ctr + j) >> 32) as u32;
+        x[self.counter] = u32x4(lo(0), lo(1), lo(2), lo(3));
+        if !self.narrow {
+            x[self.counter + 1] = u32x4(hi(0), hi(1), hi(2), hi(3));
+        }
+
+        let input = x;
+        permute4(&mut x, self.double_rounds);
+        for i in range(0u, 16) {
+            let u32x4(a, b, c, d) = x[i] + input[i];
+            store_32l!(a, self.buf[mut 4*i..]);
+            store_32l!(b, self.buf[mut 64 + 4*i..]);
+            store_32l!(c, self.buf[mut 128 + 4*i..]);
+            store_32l!(d, self.buf[mut 192 + 4*i..]);
+        }
+        self.buf_len = 256;
+        self.bump_counter(4);
+    }
+
+    fn run_eight_blocks(&mut self, permute8: Permute8) {
+        let mut x = [[0u32, ..8], ..16];
+        for i in range(0u, 16) {
+            x[i] = [self.state[i], ..8];
+        }
+        let ctr = self.next_block();
+        for j in range(0u, 8) {
+            x[self.counter][j] = (ctr + j as u64) as u32;
+            if !self.narrow {
+                x[self.counter + 1][j] = ((ctr + j as u64) >> 32) as u32;
+            }
+        }
+
+        let input = x;
+        permute8(&mut x, self.double_rounds);
+        for i in range(0u, 16) {
+            for j in range(0u, 8) {
+                store_32l!(x[i][j] + input[i][j], self.buf[mut 64*j + 4*i..]);
+            }
+        }
+        self.buf_len = 512;
+        self.bump_counter(8);
+    }
+
+    fn bump_counter(&mut self, blocks: u32) {
+        let old = self.state[self.counter];
+        let new = old + blocks;
+        self.state[self.counter] = new;
+        if new < old {
+            if self.narrow {
+                self.exhausted = true;
+            } else {
+                self.state[self.counter + 1] += 1;
+            }
+        }
+    }
+}
+
+/// `out = a ^ b`, a u64 at a time and then the rest byte by byte. XOR is
+/// the same in any byte order, so the words are loaded and stored as they
+/// lie in memory; an eight-byte copy becomes a single unaligned load or
+/// store.
+fn xor_into(a: &[u8], b: &[u8], out: &mut [u8]) {
+    assert!(a.len() >= out.len() && b.len() >= out.len());
+    let words = out.len() / 8;
+    for i in range(0, words) {
+        let offset = (8 * i) as int;
+        let (mut x, mut y) = (0u64, 0u64);
+        unsafe {
+            copy_nonoverlapping_memory(&mut x as *mut u64 as *mut u8, a.as_ptr().offset(offset), 8);
+            copy_nonoverlapping_memory(&mut y as *mut u64 as *mut u8, b.as_ptr().offset(offset), 8);
+            x ^= y;
+            copy_nonoverlapping_memory(out.as_mut_ptr().offset(offset),
+                                       &x as *const u64 as *const u8, 8);
+        }
+    }
+    for i in range(words * 8, out.len()) {
+        out[i] = a[i] ^ b[i];
+    }
+}
+
+impl Drop for Keystream {
+    #[inline(never)]
+    fn drop(&mut self) {
+        for b in self.state.iter_mut() {
+            *b = 0;
+        }
+        for b in self.buf.iter_mut() {
+            *b = 0;
+        }
+    }
+}
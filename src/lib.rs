@@ -2,22 +2,86 @@
 #![feature(macro_rules)]
 #![feature(phase)]
 #![feature(unsafe_destructor)]
+#![feature(asm)]
 
 #[phase(plugin, link)]
 extern crate log;
 extern crate serialize;
 extern crate num;
+#[cfg(test)]
+extern crate test;
 
 mod macros;
 pub mod sha1;
+pub mod md5;
+pub mod sha2;
+pub mod sha3;
+pub mod blake2;
+pub mod blake3;
+pub mod hmac;
+pub mod hkdf;
+pub mod pbkdf2;
+pub mod scrypt;
+pub mod argon2;
+pub mod bcrypt;
+pub mod unixcrypt;
+pub mod constanttime;
 pub mod aes;
+pub mod blowfish;
 pub mod cipher;
 mod securemem;
+mod keystream;
 pub mod chacha20;
+pub mod salsa20;
+pub mod poly1305;
+pub mod chacha20poly1305;
+pub mod gcm;
 pub mod rsa;
 
-pub trait HashFn {
+/// Hash states are `Clone` so that a prefix can be hashed once and then
+/// shared, as HMAC-based constructions do with their keyed states.
+/// States holding arrays longer than 32 elements implement `Clone` by hand,
+/// since it can't be derived for those.
+pub trait HashFn: Clone {
     fn create() -> Self;
     fn update(&mut self, data: &[u8]);
     fn digest(self) -> Vec<u8>;
+
+    /// The size of the blocks the hash function consumes, in bytes. (The
+    /// argument is a workaround for a Rust bug, as in `BlockCipher`.)
+    fn block_size(_: Option<&Self>) -> uint;
+
+    /// The length of the digest of a state made by `create`, in bytes.
+    fn output_size(_: Option<&Self>) -> uint;
+}
+
+pub fn hash_block_size<H: HashFn>() -> uint {
+    HashFn::block_size(None::<&H>)
+}
+
+pub fn hash_output_size<H: HashFn>() -> uint {
+    HashFn::output_size(None::<&H>)
+}
+
+/// Hashes `input` in one go and hex-encodes the digest, for tests.
+#[cfg(test)]
+fn hex_digest<H: HashFn>(input: &[u8]) -> String {
+    use serialize::hex::ToHex;
+    let mut h: H = HashFn::create();
+    h.update(input);
+    h.digest()[].to_hex()
+}
+
+/// A reader over the output of an extendable-output function, which can be
+/// squeezed for as many bytes as the caller wants.
+pub trait XofReader {
+    fn read(&mut self, out: &mut [u8]);
+}
+
+/// Like `HashFn`, but with an output stream of arbitrary length instead of
+/// a fixed-size digest.
+pub trait ExtendableOutputFn<R: XofReader> {
+    fn create() -> Self;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> R;
 }
@@ -91,6 +91,32 @@ macro_rules! store_64h (
     })
 )
 
+macro_rules! get_64h (
+    ($x: expr, $i: expr) => (
+        (($x[$i * 8] as u64) << 56) |
+        (($x[$i * 8 + 1] as u64) << 48) |
+        (($x[$i * 8 + 2] as u64) << 40) |
+        (($x[$i * 8 + 3] as u64) << 32) |
+        (($x[$i * 8 + 4] as u64) << 24) |
+        (($x[$i * 8 + 5] as u64) << 16) |
+        (($x[$i * 8 + 6] as u64) << 8)  |
+        ($x[$i * 8 + 7] as u64)
+    )
+)
+
+macro_rules! get_64l (
+    ($x: expr, $i: expr) => (
+        ($x[$i * 8] as u64) |
+        (($x[$i * 8 + 1] as u64) << 8)  |
+        (($x[$i * 8 + 2] as u64) << 16) |
+        (($x[$i * 8 + 3] as u64) << 24) |
+        (($x[$i * 8 + 4] as u64) << 32) |
+        (($x[$i * 8 + 5] as u64) << 40) |
+        (($x[$i * 8 + 6] as u64) << 48) |
+        (($x[$i * 8 + 7] as u64) << 56)
+    )
+)
+
 macro_rules! be_word (
     ($a: expr, $b: expr, $c: expr, $d: expr) => (
            (($a as u32) << 24) | (($b as u32) << 16)
@@ -116,10 +142,175 @@ macro_rules! cc20_qround (
     })
 )
 
+macro_rules! rotl_lanes (
+    ($val: expr, $shift: expr) => (
+        {
+            let val = $val;
+            let (l, r) = ($shift, 32 - $shift);
+            (val << ::std::simd::u32x4(l, l, l, l)) | (val >> ::std::simd::u32x4(r, r, r, r))
+        }
+    )
+)
+
+macro_rules! cc20_qround_lanes (
+    ($st: expr, $a: expr, $b: expr, $c: expr, $d: expr) => ({
+        $st[$a] = $st[$a] + $st[$b]; $st[$d] = rotl_lanes!($st[$d] ^ $st[$a], 16);
+        $st[$c] = $st[$c] + $st[$d]; $st[$b] = rotl_lanes!($st[$b] ^ $st[$c], 12);
+        $st[$a] = $st[$a] + $st[$b]; $st[$d] = rotl_lanes!($st[$d] ^ $st[$a], 8);
+        $st[$c] = $st[$c] + $st[$d]; $st[$b] = rotl_lanes!($st[$b] ^ $st[$c], 7);
+    })
+)
+
+macro_rules! salsa20_qround (
+    ($st: expr, $a: expr, $b: expr, $c: expr, $d: expr) => ({
+        $st[$b] ^= rotl!($st[$a] + $st[$d], 7);
+        $st[$c] ^= rotl!($st[$b] + $st[$a], 9);
+        $st[$d] ^= rotl!($st[$c] + $st[$b], 13);
+        $st[$a] ^= rotl!($st[$d] + $st[$c], 18);
+    })
+)
+
 macro_rules! unpack (
     ($s: expr, $i: expr) => (le_word!($s[$i*4 + 0], $s[$i*4 + 1], $s[$i*4 + 2], $s[$i*4 + 3]))
 )
 
 macro_rules! clone_helper (
     ($s: expr, $($i: expr),*) => ([$($s[$i].clone()),*])
-)
\ No newline at end of file
+)
+macro_rules! md5_f (
+    ($x: expr, $y: expr, $z: expr) => ($z ^ ($x & ($y ^ $z)))
+)
+macro_rules! md5_g (
+    ($x: expr, $y: expr, $z: expr) => ($y ^ ($z & ($x ^ $y)))
+)
+macro_rules! md5_h (
+    ($x: expr, $y: expr, $z: expr) => ($x ^ $y ^ $z)
+)
+macro_rules! md5_i (
+    ($x: expr, $y: expr, $z: expr) => ($y ^ ($x | !$z))
+)
+
+macro_rules! md5_ff (
+    ($a: ident, $b: ident, $c: ident, $d: ident, $m: expr, $s: expr, $t: expr) => ({
+        $a = rotl!($a + md5_f!($b, $c, $d) + $m + $t, $s) + $b;
+    })
+)
+macro_rules! md5_gg (
+    ($a: ident, $b: ident, $c: ident, $d: ident, $m: expr, $s: expr, $t: expr) => ({
+        $a = rotl!($a + md5_g!($b, $c, $d) + $m + $t, $s) + $b;
+    })
+)
+macro_rules! md5_hh (
+    ($a: ident, $b: ident, $c: ident, $d: ident, $m: expr, $s: expr, $t: expr) => ({
+        $a = rotl!($a + md5_h!($b, $c, $d) + $m + $t, $s) + $b;
+    })
+)
+macro_rules! md5_ii (
+    ($a: ident, $b: ident, $c: ident, $d: ident, $m: expr, $s: expr, $t: expr) => ({
+        $a = rotl!($a + md5_i!($b, $c, $d) + $m + $t, $s) + $b;
+    })
+)
+
+macro_rules! store_32l (
+    ($x: expr, $y: expr) => ({
+        $y[0] = (($x)       & 255) as u8;
+        $y[1] = (($x >> 8)  & 255) as u8;
+        $y[2] = (($x >> 16) & 255) as u8;
+        $y[3] = (($x >> 24) & 255) as u8;
+    })
+)
+
+macro_rules! store_64l (
+    ($x: expr, $y: expr) => ({
+        $y[0] = (($x)       & 255) as u8;
+        $y[1] = (($x >> 8)  & 255) as u8;
+        $y[2] = (($x >> 16) & 255) as u8;
+        $y[3] = (($x >> 24) & 255) as u8;
+        $y[4] = (($x >> 32) & 255) as u8;
+        $y[5] = (($x >> 40) & 255) as u8;
+        $y[6] = (($x >> 48) & 255) as u8;
+        $y[7] = (($x >> 56) & 255) as u8;
+    })
+)
+
+macro_rules! sha256_ch (
+    ($x: expr, $y: expr, $z: expr) => ($z ^ ($x & ($y ^ $z)))
+)
+macro_rules! sha256_maj (
+    ($x: expr, $y: expr, $z: expr) => ((($x | $y) & $z) | ($x & $y))
+)
+macro_rules! sha256_s0 (
+    ($x: expr) => (rotr!($x, 2) ^ rotr!($x, 13) ^ rotr!($x, 22))
+)
+macro_rules! sha256_s1 (
+    ($x: expr) => (rotr!($x, 6) ^ rotr!($x, 11) ^ rotr!($x, 25))
+)
+macro_rules! sha256_g0 (
+    ($x: expr) => (rotr!($x, 7) ^ rotr!($x, 18) ^ ($x >> 3))
+)
+macro_rules! sha256_g1 (
+    ($x: expr) => (rotr!($x, 17) ^ rotr!($x, 19) ^ ($x >> 10))
+)
+
+macro_rules! sha512_s0 (
+    ($x: expr) => (rotr!($x, 28) ^ rotr!($x, 34) ^ rotr!($x, 39))
+)
+macro_rules! sha512_s1 (
+    ($x: expr) => (rotr!($x, 14) ^ rotr!($x, 18) ^ rotr!($x, 41))
+)
+macro_rules! sha512_g0 (
+    ($x: expr) => (rotr!($x, 1) ^ rotr!($x, 8) ^ ($x >> 7))
+)
+macro_rules! sha512_g1 (
+    ($x: expr) => (rotr!($x, 19) ^ rotr!($x, 61) ^ ($x >> 6))
+)
+
+macro_rules! blake2_g (
+    ($v: expr, $a: expr, $b: expr, $c: expr, $d: expr, $x: expr, $y: expr,
+     $r1: expr, $r2: expr, $r3: expr, $r4: expr) => ({
+        $v[$a] = $v[$a] + $v[$b] + $x; $v[$d] = rotr!($v[$d] ^ $v[$a], $r1);
+        $v[$c] = $v[$c] + $v[$d];      $v[$b] = rotr!($v[$b] ^ $v[$c], $r2);
+        $v[$a] = $v[$a] + $v[$b] + $y; $v[$d] = rotr!($v[$d] ^ $v[$a], $r3);
+        $v[$c] = $v[$c] + $v[$d];      $v[$b] = rotr!($v[$b] ^ $v[$c], $r4);
+    })
+)
+
+macro_rules! blamka (
+    ($x: expr, $y: expr) => ($x + $y + 2 * ($x & 0xffffffff) * ($y & 0xffffffff))
+)
+
+macro_rules! argon2_gb (
+    ($v: expr, $a: expr, $b: expr, $c: expr, $d: expr) => ({
+        $v[$a] = blamka!($v[$a], $v[$b]); $v[$d] = rotr!($v[$d] ^ $v[$a], 32);
+        $v[$c] = blamka!($v[$c], $v[$d]); $v[$b] = rotr!($v[$b] ^ $v[$c], 24);
+        $v[$a] = blamka!($v[$a], $v[$b]); $v[$d] = rotr!($v[$d] ^ $v[$a], 16);
+        $v[$c] = blamka!($v[$c], $v[$d]); $v[$b] = rotr!($v[$b] ^ $v[$c], 63);
+    })
+)
+
+#[test]
+fn test_byte_order() {
+    // These only shift and mask, so the results mustn't depend on the
+    // host's byte order.
+    let bytes = [0x01u8, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+
+    assert_eq!(be_word!(0x01u8, 0x23u8, 0x45u8, 0x67u8), 0x01234567u32);
+    assert_eq!(le_word!(0x01u8, 0x23u8, 0x45u8, 0x67u8), 0x67452301u32);
+    assert_eq!(be_unword!(0x01234567u32), (0x01u8, 0x23u8, 0x45u8, 0x67u8));
+
+    assert_eq!(unpack!(bytes, 0), 0x67452301u32);
+    assert_eq!(unpack!(bytes, 1), 0xefcdab89u32);
+    assert_eq!(get_32h!(bytes, 0), 0x01234567u32);
+    assert_eq!(get_32h!(bytes, 1), 0x89abcdefu32);
+    assert_eq!(get_64h!(bytes, 0), 0x0123456789abcdefu64);
+    assert_eq!(get_64l!(bytes, 0), 0xefcdab8967452301u64);
+
+    let mut out = [0u8, ..8];
+    store_32h!(0x01234567u32, out[mut ..4]);
+    store_32l!(0xefcdab89u32, out[mut 4..]);
+    assert_eq!(out, bytes);
+    store_64h!(0x0123456789abcdefu64, out[mut]);
+    assert_eq!(out, bytes);
+    store_64l!(0xefcdab8967452301u64, out[mut]);
+    assert_eq!(out, bytes);
+}
@@ -1,40 +1,225 @@
 use HashFn;
 
-pub struct Md5 {
+use std::cmp::min;
+use std::slice::bytes;
+
+pub const BLOCK_SIZE: uint = 64;
+
+#[deriving(Clone)]
+struct Md5_ {
     a: u32,
     b: u32,
     c: u32,
     d: u32,
-    total: [u32, ..2],
-    buflen: u32,
-    buffer: [u32, ..32]
+    curlen: uint,
+    length: u64,
+}
+
+pub struct Md5 {
+    s: Md5_,
+    buf: [u8, ..BLOCK_SIZE],
+}
+
+impl Clone for Md5 {
+    fn clone(&self) -> Md5 {
+        Md5 { s: self.s.clone(), buf: self.buf }
+    }
+}
+
+impl Md5_ {
+    fn compress(&mut self, data: &[u8]) {
+        assert!(data.len() >= BLOCK_SIZE);
+        let (mut a, mut b, mut c, mut d) = (self.a, self.b, self.c, self.d);
+        let mut x = [0u32, ..16];
+
+        for i in range(0u, 16) {
+            x[i] = unpack!(data, i);
+        }
+
+        md5_ff!(a, b, c, d, x[ 0],  7, 0xd76aa478);
+        md5_ff!(d, a, b, c, x[ 1], 12, 0xe8c7b756);
+        md5_ff!(c, d, a, b, x[ 2], 17, 0x242070db);
+        md5_ff!(b, c, d, a, x[ 3], 22, 0xc1bdceee);
+        md5_ff!(a, b, c, d, x[ 4],  7, 0xf57c0faf);
+        md5_ff!(d, a, b, c, x[ 5], 12, 0x4787c62a);
+        md5_ff!(c, d, a, b, x[ 6], 17, 0xa8304613);
+        md5_ff!(b, c, d, a, x[ 7], 22, 0xfd469501);
+        md5_ff!(a, b, c, d, x[ 8],  7, 0x698098d8);
+        md5_ff!(d, a, b, c, x[ 9], 12, 0x8b44f7af);
+        md5_ff!(c, d, a, b, x[10], 17, 0xffff5bb1);
+        md5_ff!(b, c, d, a, x[11], 22, 0x895cd7be);
+        md5_ff!(a, b, c, d, x[12],  7, 0x6b901122);
+        md5_ff!(d, a, b, c, x[13], 12, 0xfd987193);
+        md5_ff!(c, d, a, b, x[14], 17, 0xa679438e);
+        md5_ff!(b, c, d, a, x[15], 22, 0x49b40821);
+
+        md5_gg!(a, b, c, d, x[ 1],  5, 0xf61e2562);
+        md5_gg!(d, a, b, c, x[ 6],  9, 0xc040b340);
+        md5_gg!(c, d, a, b, x[11], 14, 0x265e5a51);
+        md5_gg!(b, c, d, a, x[ 0], 20, 0xe9b6c7aa);
+        md5_gg!(a, b, c, d, x[ 5],  5, 0xd62f105d);
+        md5_gg!(d, a, b, c, x[10],  9, 0x02441453);
+        md5_gg!(c, d, a, b, x[15], 14, 0xd8a1e681);
+        md5_gg!(b, c, d, a, x[ 4], 20, 0xe7d3fbc8);
+        md5_gg!(a, b, c, d, x[ 9],  5, 0x21e1cde6);
+        md5_gg!(d, a, b, c, x[14],  9, 0xc33707d6);
+        md5_gg!(c, d, a, b, x[ 3], 14, 0xf4d50d87);
+        md5_gg!(b, c, d, a, x[ 8], 20, 0x455a14ed);
+        md5_gg!(a, b, c, d, x[13],  5, 0xa9e3e905);
+        md5_gg!(d, a, b, c, x[ 2],  9, 0xfcefa3f8);
+        md5_gg!(c, d, a, b, x[ 7], 14, 0x676f02d9);
+        md5_gg!(b, c, d, a, x[12], 20, 0x8d2a4c8a);
+
+        md5_hh!(a, b, c, d, x[ 5],  4, 0xfffa3942);
+        md5_hh!(d, a, b, c, x[ 8], 11, 0x8771f681);
+        md5_hh!(c, d, a, b, x[11], 16, 0x6d9d6122);
+        md5_hh!(b, c, d, a, x[14], 23, 0xfde5380c);
+        md5_hh!(a, b, c, d, x[ 1],  4, 0xa4beea44);
+        md5_hh!(d, a, b, c, x[ 4], 11, 0x4bdecfa9);
+        md5_hh!(c, d, a, b, x[ 7], 16, 0xf6bb4b60);
+        md5_hh!(b, c, d, a, x[10], 23, 0xbebfbc70);
+        md5_hh!(a, b, c, d, x[13],  4, 0x289b7ec6);
+        md5_hh!(d, a, b, c, x[ 0], 11, 0xeaa127fa);
+        md5_hh!(c, d, a, b, x[ 3], 16, 0xd4ef3085);
+        md5_hh!(b, c, d, a, x[ 6], 23, 0x04881d05);
+        md5_hh!(a, b, c, d, x[ 9],  4, 0xd9d4d039);
+        md5_hh!(d, a, b, c, x[12], 11, 0xe6db99e5);
+        md5_hh!(c, d, a, b, x[15], 16, 0x1fa27cf8);
+        md5_hh!(b, c, d, a, x[ 2], 23, 0xc4ac5665);
+
+        md5_ii!(a, b, c, d, x[ 0],  6, 0xf4292244);
+        md5_ii!(d, a, b, c, x[ 7], 10, 0x432aff97);
+        md5_ii!(c, d, a, b, x[14], 15, 0xab9423a7);
+        md5_ii!(b, c, d, a, x[ 5], 21, 0xfc93a039);
+        md5_ii!(a, b, c, d, x[12],  6, 0x655b59c3);
+        md5_ii!(d, a, b, c, x[ 3], 10, 0x8f0ccc92);
+        md5_ii!(c, d, a, b, x[10], 15, 0xffeff47d);
+        md5_ii!(b, c, d, a, x[ 1], 21, 0x85845dd1);
+        md5_ii!(a, b, c, d, x[ 8],  6, 0x6fa87e4f);
+        md5_ii!(d, a, b, c, x[15], 10, 0xfe2ce6e0);
+        md5_ii!(c, d, a, b, x[ 6], 15, 0xa3014314);
+        md5_ii!(b, c, d, a, x[13], 21, 0x4e0811a1);
+        md5_ii!(a, b, c, d, x[ 4],  6, 0xf7537e82);
+        md5_ii!(d, a, b, c, x[11], 10, 0xbd3af235);
+        md5_ii!(c, d, a, b, x[ 2], 15, 0x2ad7d2bb);
+        md5_ii!(b, c, d, a, x[ 9], 21, 0xeb86d391);
+
+        self.a += a;
+        self.b += b;
+        self.c += c;
+        self.d += d;
+    }
 }
 
 impl HashFn for Md5 {
     fn create() -> Md5 {
         Md5 {
-            a: 0x67452301,
-            b: 0xefcdab89,
-            c: 0x98badcfe,
-            d: 0x10325476,
-            total: [0, 0],
-            buflen: 0,
-            buffer: [0, ..32]
+            s: Md5_ {
+                a: 0x67452301,
+                b: 0xefcdab89,
+                c: 0x98badcfe,
+                d: 0x10325476,
+                curlen: 0,
+                length: 0,
+            },
+            buf: [0, ..BLOCK_SIZE]
         }
     }
 
     fn update(&mut self, data: &[u8]) {
-        if self.buflen != 0 {
+        assert!(self.s.curlen <= self.buf.len());
+        let mut inlen = data.len();
+        let mut cur_pos = 0;
 
+        while inlen > 0 {
+            if self.s.curlen == 0 && inlen >= BLOCK_SIZE {
+                self.s.compress(data.slice_from(cur_pos));
+                self.s.length += (BLOCK_SIZE as u64) * 8;
+                cur_pos += BLOCK_SIZE;
+                inlen -= BLOCK_SIZE;
+            } else {
+                let n = min(inlen, BLOCK_SIZE - self.s.curlen);
+                bytes::copy_memory(self.buf[mut self.s.curlen..], data[cur_pos..cur_pos + n]);
+                self.s.curlen += n;
+                cur_pos += n;
+                inlen -= n;
+                if self.s.curlen == BLOCK_SIZE {
+                    self.s.compress(&self.buf);
+                    self.s.length += (BLOCK_SIZE as u64) * 8;
+                    self.s.curlen = 0;
+                }
+            }
         }
     }
 
-    fn digest(self) -> Vec<u8> {
+    fn digest(mut self) -> Vec<u8> {
+        assert!(self.s.curlen < self.buf.len());
+
+        self.s.length += (self.s.curlen as u64) * 8;
+        self.buf[self.s.curlen] = 0x80;
+        self.s.curlen += 1;
+
+        if self.s.curlen > 56 {
+            while self.s.curlen < 64 {
+                self.buf[self.s.curlen] = 0;
+                self.s.curlen += 1;
+            }
+            self.s.compress(&self.buf);
+            self.s.curlen = 0;
+        }
+
+        while self.s.curlen < 56 {
+            self.buf[self.s.curlen] = 0;
+            self.s.curlen += 1;
+        }
+
+        // MD5 is little-endian throughout, unlike SHA-1
+        store_64l!(self.s.length, self.buf.slice_from_mut(56));
+        self.s.compress(&self.buf);
+
         let mut out = Vec::from_elem(16, 0u8);
-        store_32h!(self.a, out.mut_slice(0, 4));
-        store_32h!(self.b, out.mut_slice(4, 8));
-        store_32h!(self.c, out.mut_slice(8, 12));
-        store_32h!(self.d, out.mut_slice(12, 16));
+        store_32l!(self.s.a, out.as_mut_slice());
+        store_32l!(self.s.b, out.slice_from_mut(4));
+        store_32l!(self.s.c, out.slice_from_mut(8));
+        store_32l!(self.s.d, out.slice_from_mut(12));
+
         return out;
     }
+
+    fn block_size(_: Option<&Md5>) -> uint { BLOCK_SIZE }
+
+    fn output_size(_: Option<&Md5>) -> uint { 16 }
+}
+
+#[test]
+fn test_md5_rfc1321() {
+    use serialize::hex::ToHex;
+    static VECTORS: &'static [(&'static str, &'static str)] = &[
+        ("", "d41d8cd98f00b204e9800998ecf8427e"),
+        ("a", "0cc175b9c0f1b6a831c399e269772661"),
+        ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+        ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+        ("abcdefghijklmnopqrstuvwxyz", "c3fcd3d76192e4007dfb496cca67e13b"),
+        ("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+         "d174ab98d277d9f5a5611c2c9f419d9f"),
+        ("12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+         "57edf4a22be3c955ac49da2e2107b67a"),
+    ];
+
+    for &(input, output) in VECTORS.iter() {
+        let mut md5: Md5 = HashFn::create();
+        md5.update(input.as_bytes());
+        assert_eq!(md5.digest()[].to_hex()[], output);
+    }
+}
+
+#[test]
+fn test_md5_incremental() {
+    use serialize::hex::ToHex;
+    let input = b"12345678901234567890123456789012345678901234567890123456789012345678901234567890";
+    let mut md5: Md5 = HashFn::create();
+    for chunk in input.chunks(7) {
+        md5.update(chunk);
+    }
+    assert_eq!(md5.digest()[].to_hex()[], "57edf4a22be3c955ac49da2e2107b67a");
 }
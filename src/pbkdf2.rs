@@ -0,0 +1,79 @@
+use HashFn;
+use hmac::Hmac;
+use securemem::SecureMem;
+
+use std::cmp::min;
+use std::slice::bytes;
+
+/// PBKDF2 (RFC 8018) with HMAC as the PRF, filling all of `out`.
+///
+/// The password is only keyed into HMAC once; every iteration starts from a
+/// clone of those keyed inner and outer states, so each round costs two
+/// compressions instead of four.
+pub fn pbkdf2<H: HashFn>(password: &[u8], salt: &[u8], iterations: uint, out: &mut [u8]) {
+    assert!(iterations > 0);
+    let prf: Hmac<H> = Hmac::new(password);
+
+    let mut block = 1u32;
+    let mut pos = 0;
+    while pos < out.len() {
+        let mut counter = [0u8, ..4];
+        store_32h!(block, counter[mut]);
+
+        let mut mac = prf.clone();
+        mac.update(salt);
+        mac.update(&counter);
+        let mut u = SecureMem::new(mac.finalize());
+        let mut t = SecureMem::new((*u).clone());
+
+        for _ in range(1, iterations) {
+            let mut mac = prf.clone();
+            mac.update(u.as_slice());
+            u = SecureMem::new(mac.finalize());
+            for (a, &b) in t.iter_mut().zip(u.iter()) {
+                *a ^= b;
+            }
+        }
+
+        let n = min(out.len() - pos, t.len());
+        bytes::copy_memory(out[mut pos..pos + n], t.slice_to(n));
+        pos += n;
+        block += 1;
+    }
+}
+
+#[cfg(test)]
+fn hex_pbkdf2<H: HashFn>(password: &[u8], salt: &[u8], iterations: uint, len: uint) -> String {
+    use serialize::hex::ToHex;
+    let mut out = Vec::from_elem(len, 0u8);
+    pbkdf2::<H>(password, salt, iterations, out[mut]);
+    out[].to_hex()
+}
+
+#[test]
+fn test_pbkdf2_sha1_rfc6070() {
+    use sha1::Sha1;
+    // The 16777216-iteration case is left out; it takes minutes.
+    assert_eq!(hex_pbkdf2::<Sha1>(b"password", b"salt", 1, 20)[],
+               "0c60c80f961f0e71f3a9b524af6012062fe037a6");
+    assert_eq!(hex_pbkdf2::<Sha1>(b"password", b"salt", 2, 20)[],
+               "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957");
+    assert_eq!(hex_pbkdf2::<Sha1>(b"password", b"salt", 4096, 20)[],
+               "4b007901b765489abead49d926f721d065a429c1");
+    assert_eq!(hex_pbkdf2::<Sha1>(b"passwordPASSWORDpassword",
+                                  b"saltSALTsaltSALTsaltSALTsaltSALTsalt", 4096, 25)[],
+               "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038");
+    assert_eq!(hex_pbkdf2::<Sha1>(b"pass\0word", b"sa\0lt", 4096, 16)[],
+               "56fa6aa75548099dcc37d7f03425e0c3");
+}
+
+#[test]
+fn test_pbkdf2_sha256_rfc7914() {
+    use sha2::Sha256;
+    assert_eq!(hex_pbkdf2::<Sha256>(b"passwd", b"salt", 1, 64)[],
+               "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+                49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783");
+    assert_eq!(hex_pbkdf2::<Sha256>(b"Password", b"NaCl", 80000, 64)[],
+               "4ddcd8f60b98be21830cee5ef22701f9641a4418d04c0414aeff08876b34ab56\
+                a1d425a1225833549adb841b51c9b3176a272bdebba1d078478f62b397f33c8d");
+}
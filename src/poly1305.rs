@@ -0,0 +1,234 @@
+use constanttime::ConstantTimeEq;
+
+use std::cmp::min;
+
+/// A little-endian word at any byte offset, not just a multiple of four.
+fn load_32l(m: &[u8], i: uint) -> u32 {
+    le_word!(m[i], m[i + 1], m[i + 2], m[i + 3])
+}
+
+/// The Poly1305 one-time authenticator (RFC 8439), with the accumulator in
+/// five 26-bit limbs. A key must never be used for more than one message.
+#[deriving(Clone)]
+pub struct Poly1305 {
+    r: [u32, ..5],
+    h: [u32, ..5],
+    pad: [u32, ..4],
+    buffer: [u8, ..16],
+    leftover: uint,
+}
+
+impl Poly1305 {
+    /// Returns None unless `key` is 32 bytes: `r` followed by `s`.
+    pub fn new(key: &[u8]) -> Option<Poly1305> {
+        if key.len() != 32 {
+            return None;
+        }
+        Some(Poly1305 {
+            // r is clamped as it's split into limbs.
+            r: [
+                load_32l(key, 0) & 0x3ffffff,
+                (load_32l(key, 3) >> 2) & 0x3ffff03,
+                (load_32l(key, 6) >> 4) & 0x3ffc0ff,
+                (load_32l(key, 9) >> 6) & 0x3f03fff,
+                (load_32l(key, 12) >> 8) & 0x00fffff,
+                ],
+            h: [0, ..5],
+            pad: [unpack!(key, 4), unpack!(key, 5), unpack!(key, 6), unpack!(key, 7)],
+            buffer: [0, ..16],
+            leftover: 0,
+        })
+    }
+
+    /// Absorbs one 16-byte block. `hibit` is the 2^128 bit that's appended
+    /// to every full block, and left out of the already padded final one.
+    fn block(&mut self, m: &[u8], hibit: u32) {
+        let (r0, r1, r2, r3, r4) = (self.r[0], self.r[1], self.r[2], self.r[3], self.r[4]);
+        let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+
+        let h0 = (self.h[0] + (load_32l(m, 0) & 0x3ffffff)) as u64;
+        let h1 = (self.h[1] + ((load_32l(m, 3) >> 2) & 0x3ffffff)) as u64;
+        let h2 = (self.h[2] + ((load_32l(m, 6) >> 4) & 0x3ffffff)) as u64;
+        let h3 = (self.h[3] + ((load_32l(m, 9) >> 6) & 0x3ffffff)) as u64;
+        let h4 = (self.h[4] + ((load_32l(m, 12) >> 8) | hibit)) as u64;
+
+        let (r0, r1, r2, r3, r4) = (r0 as u64, r1 as u64, r2 as u64, r3 as u64, r4 as u64);
+        let (s1, s2, s3, s4) = (s1 as u64, s2 as u64, s3 as u64, s4 as u64);
+
+        let d0 = h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1;
+        let mut d1 = h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2;
+        let mut d2 = h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3;
+        let mut d3 = h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4;
+        let mut d4 = h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0;
+
+        // Partial reduction mod 2^130 - 5.
+        let mut c = d0 >> 26;
+        self.h[0] = (d0 as u32) & 0x3ffffff;
+        d1 += c; c = d1 >> 26; self.h[1] = (d1 as u32) & 0x3ffffff;
+        d2 += c; c = d2 >> 26; self.h[2] = (d2 as u32) & 0x3ffffff;
+        d3 += c; c = d3 >> 26; self.h[3] = (d3 as u32) & 0x3ffffff;
+        d4 += c; c = d4 >> 26; self.h[4] = (d4 as u32) & 0x3ffffff;
+        self.h[0] += (c as u32) * 5;
+        let c = self.h[0] >> 26;
+        self.h[0] &= 0x3ffffff;
+        self.h[1] += c;
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let mut m = data;
+
+        if self.leftover > 0 {
+            let n = min(16 - self.leftover, m.len());
+            for i in range(0, n) {
+                self.buffer[self.leftover + i] = m[i];
+            }
+            self.leftover += n;
+            m = m[n..];
+            if self.leftover < 16 {
+                return;
+            }
+            let buffer = self.buffer;
+            self.block(&buffer, 1 << 24);
+            self.leftover = 0;
+        }
+
+        while m.len() >= 16 {
+            self.block(m[..16], 1 << 24);
+            m = m[16..];
+        }
+
+        for i in range(0, m.len()) {
+            self.buffer[i] = m[i];
+        }
+        self.leftover = m.len();
+    }
+
+    /// Returns the 16-byte tag.
+    pub fn finalize(mut self) -> Vec<u8> {
+        if self.leftover > 0 {
+            self.buffer[self.leftover] = 1;
+            for i in range(self.leftover + 1, 16) {
+                self.buffer[i] = 0;
+            }
+            let buffer = self.buffer;
+            self.block(&buffer, 0);
+        }
+
+        let (mut h0, mut h1, mut h2) = (self.h[0], self.h[1], self.h[2]);
+        let (mut h3, mut h4) = (self.h[3], self.h[4]);
+        let mut c = h1 >> 26;
+        h1 &= 0x3ffffff;
+        h2 += c; c = h2 >> 26; h2 &= 0x3ffffff;
+        h3 += c; c = h3 >> 26; h3 &= 0x3ffffff;
+        h4 += c; c = h4 >> 26; h4 &= 0x3ffffff;
+        h0 += c * 5; c = h0 >> 26; h0 &= 0x3ffffff;
+        h1 += c;
+
+        // g = h + 5 - 2^130; if that didn't borrow, h was at least p and g
+        // is the reduced value. The choice is made with masks, not a branch.
+        let mut g0 = h0 + 5; c = g0 >> 26; g0 &= 0x3ffffff;
+        let mut g1 = h1 + c; c = g1 >> 26; g1 &= 0x3ffffff;
+        let mut g2 = h2 + c; c = g2 >> 26; g2 &= 0x3ffffff;
+        let mut g3 = h3 + c; c = g3 >> 26; g3 &= 0x3ffffff;
+        let mut g4 = h4 + c - (1 << 26);
+
+        let mut mask = (g4 >> 31) - 1;
+        g0 &= mask; g1 &= mask; g2 &= mask; g3 &= mask; g4 &= mask;
+        mask = !mask;
+        h0 = (h0 & mask) | g0;
+        h1 = (h1 & mask) | g1;
+        h2 = (h2 & mask) | g2;
+        h3 = (h3 & mask) | g3;
+        h4 = (h4 & mask) | g4;
+
+        // Back to four 32-bit words, then add s.
+        let words = [
+            h0 | (h1 << 26),
+            (h1 >> 6) | (h2 << 20),
+            (h2 >> 12) | (h3 << 14),
+            (h3 >> 18) | (h4 << 8),
+            ];
+        let mut tag = Vec::from_elem(16, 0u8);
+        let mut f = 0u64;
+        for i in range(0u, 4) {
+            f = words[i] as u64 + self.pad[i] as u64 + (f >> 32);
+            store_32l!(f as u32, tag[mut 4*i..]);
+        }
+        tag
+    }
+
+    /// Checks `tag` against the computed one in constant time.
+    pub fn verify(self, tag: &[u8]) -> bool {
+        self.finalize()[].const_time_eq(&tag)
+    }
+}
+
+impl Drop for Poly1305 {
+    #[inline(never)]
+    fn drop(&mut self) {
+        for w in self.r.iter_mut().chain(self.h.iter_mut()).chain(self.pad.iter_mut()) {
+            *w = 0;
+        }
+        for b in self.buffer.iter_mut() {
+            *b = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+fn hex_poly1305(key: &str, msg: &[u8]) -> String {
+    use serialize::hex::{FromHex, ToHex};
+    let mut poly = Poly1305::new(key.from_hex().unwrap()[]).unwrap();
+    poly.update(msg);
+    poly.finalize()[].to_hex()
+}
+
+#[test]
+fn test_poly1305_rfc8439() {
+    use serialize::hex::FromHex;
+    // Section 2.5.2
+    assert_eq!(hex_poly1305("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b",
+                            b"Cryptographic Forum Research Group")[],
+               "a8061dc1305136c6c22b8baf0c0127a9");
+
+    // Appendix A.3, #1 and the vectors that exercise the final reduction.
+    assert_eq!(hex_poly1305("0000000000000000000000000000000000000000000000000000000000000000",
+                            &[0u8, ..64])[],
+               "00000000000000000000000000000000");
+    assert_eq!(hex_poly1305("0200000000000000000000000000000000000000000000000000000000000000",
+                            &[0xffu8, ..16])[],
+               "03000000000000000000000000000000");
+    assert_eq!(hex_poly1305("02000000000000000000000000000000ffffffffffffffffffffffffffffffff",
+                            "02000000000000000000000000000000".from_hex().unwrap()[])[],
+               "03000000000000000000000000000000");
+    assert_eq!(hex_poly1305("0100000000000000000000000000000000000000000000000000000000000000",
+                            "ffffffffffffffffffffffffffffffff\
+                             f0ffffffffffffffffffffffffffffff\
+                             11000000000000000000000000000000".from_hex().unwrap()[])[],
+               "05000000000000000000000000000000");
+}
+
+#[test]
+fn test_poly1305_incremental() {
+    use serialize::hex::FromHex;
+    let key = "85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b"
+        .from_hex().unwrap();
+    let msg = b"Cryptographic Forum Research Group";
+    let tag = "a8061dc1305136c6c22b8baf0c0127a9".from_hex().unwrap();
+
+    for split in range(0u, msg.len() + 1) {
+        let mut poly = Poly1305::new(key[]).unwrap();
+        poly.update(msg[..split]);
+        poly.update(msg[split..]);
+        assert!(poly.verify(tag[]));
+    }
+
+    let mut poly = Poly1305::new(key[]).unwrap();
+    poly.update(msg);
+    assert!(!poly.clone().verify(tag[..15]));
+    let mut bad = tag.clone();
+    bad[0] ^= 1;
+    assert!(!poly.verify(bad[]));
+
+    assert!(Poly1305::new(key[..31]).is_none());
+}
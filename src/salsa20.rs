@@ -0,0 +1,176 @@
+use cipher::StreamCipher;
+use keystream::{Keystream, Lanes};
+use securemem::SecureMem;
+
+/// Salsa20 spreads the constants along the diagonal, with the key around
+/// them and `middle` (the nonce, then the counter) in words 6 to 9.
+fn initial_state(key: &[u8], middle: [u32, ..4]) -> [u32, ..16] {
+    [
+        0x61707865,
+        unpack!(key, 0),
+        unpack!(key, 1),
+        unpack!(key, 2),
+        unpack!(key, 3),
+        0x3320646e,
+        middle[0],
+        middle[1],
+        middle[2],
+        middle[3],
+        0x79622d32,
+        unpack!(key, 4),
+        unpack!(key, 5),
+        unpack!(key, 6),
+        unpack!(key, 7),
+        0x6b206574,
+        ]
+}
+
+/// The Salsa20 permutation, without the final addition of the input.
+fn permute(state: &mut [u32, ..16], double_rounds: uint) {
+    for _ in range(0, double_rounds) {
+        salsa20_qround!(state, 0, 4, 8, 12);
+        salsa20_qround!(state, 5, 9, 13, 1);
+        salsa20_qround!(state, 10, 14, 2, 6);
+        salsa20_qround!(state, 15, 3, 7, 11);
+        salsa20_qround!(state, 0, 1, 2, 3);
+        salsa20_qround!(state, 5, 6, 7, 4);
+        salsa20_qround!(state, 10, 11, 8, 9);
+        salsa20_qround!(state, 15, 12, 13, 14);
+    }
+}
+
+/// Salsa20/20 with a 32-byte key, a 64-bit nonce and a 64-bit block
+/// counter.
+pub struct Salsa20 {
+    keystream: Keystream,
+}
+
+impl Salsa20 {
+    /// Returns None unless the key is 32 bytes and the nonce is 8.
+    pub fn new(key: &[u8], nonce: &[u8]) -> Option<Salsa20> {
+        if key.len() != 32 || nonce.len() != 8 {
+            return None;
+        }
+        let state = initial_state(key, [unpack!(nonce, 0), unpack!(nonce, 1), 0, 0]);
+        Some(Salsa20 { keystream: Keystream::new(state, 8, false, permute, Lanes::One, 10) })
+    }
+
+    /// Moves to `offset` bytes into the keystream.
+    pub fn seek(&mut self, offset: u64) {
+        self.keystream.seek(offset);
+    }
+
+    /// The offset of the next keystream byte, as `seek` takes it.
+    pub fn current_position(&self) -> u64 {
+        self.keystream.current_position()
+    }
+}
+
+impl StreamCipher for Salsa20 {
+    fn combine(&mut self, input: &[u8], output: &mut [u8]) {
+        self.keystream.combine(input, output);
+    }
+}
+
+/// HSalsa20: derives a 32-byte subkey from a 32-byte key and a 16-byte
+/// nonce, by taking the diagonal and the middle of the permuted state
+/// without the feed-forward. Returns None if either length is wrong.
+pub fn hsalsa20(key: &[u8], nonce: &[u8]) -> Option<Vec<u8>> {
+    if key.len() != 32 || nonce.len() != 16 {
+        return None;
+    }
+    let middle = [unpack!(nonce, 0), unpack!(nonce, 1), unpack!(nonce, 2), unpack!(nonce, 3)];
+    let mut state = initial_state(key, middle);
+    permute(&mut state, 10);
+
+    let mut out = Vec::from_elem(32, 0u8);
+    for (i, &w) in [0u, 5, 10, 15, 6, 7, 8, 9].iter().enumerate() {
+        store_32l!(state[w], out[mut 4*i..]);
+    }
+    for w in state.iter_mut() {
+        *w = 0;
+    }
+    Some(out)
+}
+
+/// XSalsa20: Salsa20 with a 192-bit nonce, as used by NaCl's secretbox.
+/// The key and the first 16 nonce bytes make a subkey through HSalsa20,
+/// which is used with the last 8 nonce bytes.
+pub struct XSalsa20 {
+    inner: Salsa20,
+}
+
+impl XSalsa20 {
+    /// Returns None unless the key is 32 bytes and the nonce is 24.
+    pub fn new(key: &[u8], nonce: &[u8]) -> Option<XSalsa20> {
+        if nonce.len() != 24 {
+            return None;
+        }
+        let subkey = match hsalsa20(key, nonce[..16]) {
+            Some(subkey) => SecureMem::new(subkey),
+            None => return None,
+        };
+        Salsa20::new(subkey.as_slice(), nonce[16..]).map(|inner| XSalsa20 { inner: inner })
+    }
+
+    /// Like `Salsa20::seek`.
+    pub fn seek(&mut self, offset: u64) {
+        self.inner.seek(offset);
+    }
+
+    pub fn current_position(&self) -> u64 {
+        self.inner.current_position()
+    }
+}
+
+impl StreamCipher for XSalsa20 {
+    fn combine(&mut self, input: &[u8], output: &mut [u8]) {
+        self.inner.combine(input, output);
+    }
+}
+
+#[cfg(test)]
+static KEY: &'static str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+#[test]
+fn test_salsa20() {
+    use serialize::hex::{FromHex, ToHex};
+    let key = KEY.from_hex().unwrap();
+    let nonce = "0001020304050607".from_hex().unwrap();
+
+    let mut salsa = Salsa20::new(key[], nonce[]).unwrap();
+    assert_eq!(salsa.encrypt(&[0u8, ..128])[].to_hex()[],
+               "2ead0f5f185729ced672b3a928e454f72fdb44a87b9cd8d219e4ec14aef9c6bc\
+                77bf057f5659d7753848f8d3fe769ca5fdd8057d46326990e5f136e2fcb7bb7c\
+                a13a2b59d9047b8dbeb93ec4b78ce1a59bc210641318ccce694d30ff81d2afe7\
+                bdf5a3d58cb4f9a4ed5247823e14618f06dc61cfe4b8e2ba836783b280efedca");
+
+    salsa.seek(1000);
+    assert_eq!(salsa.encrypt(&[0u8, ..64])[].to_hex()[],
+               "91e21c6be28c7619c4db48546162021a8f919e15e4c3a07e3382bf328dd1b036\
+                310c49a730881ee6f4a33270a44274a3e99a4f3987367c888a3ff1f0a7116371");
+    assert_eq!(salsa.current_position(), 1064);
+
+    assert!(Salsa20::new(key[], nonce[..4]).is_none());
+    assert!(Salsa20::new(key[..16], nonce[]).is_none());
+}
+
+#[test]
+fn test_xsalsa20() {
+    use serialize::hex::{FromHex, ToHex};
+    let key = KEY.from_hex().unwrap();
+    let nonce = "6465666768696a6b6c6d6e6f707172737475767778797a7b".from_hex().unwrap();
+
+    assert_eq!(hsalsa20(key[], nonce[..16]).unwrap()[].to_hex()[],
+               "d0e0df786a40b0aec89ca886cd211b1571660c9902c957cef234a547650a993a");
+
+    let mut xsalsa = XSalsa20::new(key[], nonce[]).unwrap();
+    assert_eq!(xsalsa.encrypt(&[0u8, ..100])[].to_hex()[],
+               "687dffe12afa5fef7e0feb195d6cd992f49572d6194281e3c87fbb4e2106932c\
+                02b999c93ab6cee9b0fd23943784a3183eaa38a7e4a64b1ba60c42940a8bc988\
+                a86ff4184f379be4f51617620013dd65c190ae181286395f03e15304dbb29e64\
+                438b4c6d");
+
+    assert!(XSalsa20::new(key[], nonce[..8]).is_none());
+    assert!(hsalsa20(key[], nonce[]).is_none());
+}
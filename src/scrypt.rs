@@ -0,0 +1,190 @@
+use pbkdf2::pbkdf2;
+use securemem::SecureMem;
+use sha2::Sha256;
+
+use std::num::Int;
+
+/// The cost parameters of scrypt. Memory use is about `128 * r * n` bytes
+/// per lane, and the `p` lanes are computed one after another.
+pub struct ScryptParams {
+    n: uint,
+    r: uint,
+    p: uint,
+}
+
+impl ScryptParams {
+    /// Returns None unless `n` is a power of two greater than 1, `r` and `p`
+    /// are nonzero, and `r * p < 2^30` as RFC 7914 requires.
+    pub fn new(n: uint, r: uint, p: uint) -> Option<ScryptParams> {
+        if n < 2 || n & (n - 1) != 0 || r == 0 || p == 0 {
+            return None;
+        }
+        match r.checked_mul(p) {
+            Some(rp) if rp < (1 << 30) => {}
+            _ => return None,
+        }
+        // So does the buffer holding all `p` lanes.
+        match r.checked_mul(p).and_then(|rp| rp.checked_mul(128)) {
+            Some(_) => {}
+            None => return None,
+        }
+        // The scratch space for one lane has to be addressable.
+        match n.checked_mul(r).and_then(|nr| nr.checked_mul(128)) {
+            Some(_) => {}
+            None => return None,
+        }
+        Some(ScryptParams { n: n, r: r, p: p })
+    }
+}
+
+/// Salsa20/8, the reduced-round Salsa20 core that scrypt mixes with.
+fn salsa20_8(b: &mut [u32]) {
+    let mut x = [0u32, ..16];
+    for i in range(0u, 16) {
+        x[i] = b[i];
+    }
+    for _ in range(0u, 4) {
+        salsa20_qround!(x, 0, 4, 8, 12);
+        salsa20_qround!(x, 5, 9, 13, 1);
+        salsa20_qround!(x, 10, 14, 2, 6);
+        salsa20_qround!(x, 15, 3, 7, 11);
+        salsa20_qround!(x, 0, 1, 2, 3);
+        salsa20_qround!(x, 5, 6, 7, 4);
+        salsa20_qround!(x, 10, 11, 8, 9);
+        salsa20_qround!(x, 15, 12, 13, 14);
+    }
+    for i in range(0u, 16) {
+        b[i] += x[i];
+    }
+}
+
+/// scryptBlockMix: `b` holds 2r 64-byte blocks as words, and `y` is scratch
+/// space of the same size.
+fn block_mix(b: &mut [u32], y: &mut [u32]) {
+    let blocks = b.len() / 16;
+    let mut x = [0u32, ..16];
+    for i in range(0u, 16) {
+        x[i] = b[(blocks - 1) * 16 + i];
+    }
+
+    for i in range(0u, blocks) {
+        for j in range(0u, 16) {
+            x[j] ^= b[i * 16 + j];
+        }
+        salsa20_8(x[mut]);
+        // Even blocks go to the first half of the output, odd ones to the
+        // second.
+        let dst = (i / 2 + (i % 2) * (blocks / 2)) * 16;
+        for j in range(0u, 16) {
+            y[dst + j] = x[j];
+        }
+    }
+
+    for i in range(0u, b.len()) {
+        b[i] = y[i];
+    }
+}
+
+/// scryptROMix over one 128r-byte lane, in place.
+fn ro_mix(lane: &mut [u8], n: uint) {
+    let words = lane.len() / 4;
+    let mut x_mem = SecureMem::new(Vec::from_elem(words, 0u32));
+    let mut y_mem = SecureMem::new(Vec::from_elem(words, 0u32));
+    let mut v_mem = SecureMem::new(Vec::from_elem(n * words, 0u32));
+    let (x, y, v) = (x_mem.as_mut_slice(), y_mem.as_mut_slice(), v_mem.as_mut_slice());
+
+    for i in range(0u, words) {
+        x[i] = unpack!(lane, i);
+    }
+
+    for i in range(0u, n) {
+        for j in range(0u, words) {
+            v[i * words + j] = x[j];
+        }
+        block_mix(x, y);
+    }
+
+    for _ in range(0u, n) {
+        // Integerify: the first word of the last 64-byte block. `n` is a
+        // power of two, so only the low bits matter.
+        let j = (x[words - 16] as uint) & (n - 1);
+        for k in range(0u, words) {
+            x[k] ^= v[j * words + k];
+        }
+        block_mix(x, y);
+    }
+
+    for i in range(0u, words) {
+        store_32l!(x[i], lane[mut 4*i..]);
+    }
+}
+
+/// scrypt (RFC 7914), filling all of `out`.
+pub fn scrypt(password: &[u8], salt: &[u8], params: &ScryptParams, out: &mut [u8]) {
+    let lane_len = 128 * params.r;
+    let len = lane_len.checked_mul(params.p).expect("scrypt parameters too large");
+    let mut b = SecureMem::new(Vec::from_elem(len, 0u8));
+    pbkdf2::<Sha256>(password, salt, 1, b.as_mut_slice());
+
+    for lane in b.as_mut_slice().chunks_mut(lane_len) {
+        ro_mix(lane, params.n);
+    }
+
+    pbkdf2::<Sha256>(password, b.as_slice(), 1, out);
+}
+
+#[test]
+fn test_salsa20_8() {
+    use serialize::hex::{FromHex, ToHex};
+    // RFC 7914, section 8
+    let input = "7e879a214f3ec9867ca940e641718f26baee555b8c61c1b50df846116dcd3b1d\
+                 ee24f319df9b3d8514121e4b5ac5aa3276021d2909c74829edebc68db8b8c25e"
+        .from_hex().unwrap();
+    let mut b = [0u32, ..16];
+    for i in range(0u, 16) {
+        b[i] = unpack!(input, i);
+    }
+    salsa20_8(b[mut]);
+    let mut out = [0u8, ..64];
+    for i in range(0u, 16) {
+        store_32l!(b[i], out[mut 4*i..]);
+    }
+    assert_eq!(out[].to_hex()[],
+               "a41f859c6608cc993b81cacb020cef05044b2181a2fd337dfd7b1c6396682f29\
+                b4393168e3c9e6bcfe6bc5b7a06d96bae424cc102c91745c24ad673dc7618f81");
+}
+
+#[test]
+fn test_scrypt_rfc7914() {
+    use serialize::hex::ToHex;
+    // The N = 2^20 case is left out; it needs a gigabyte of memory.
+    static VECTORS: &'static [(&'static str, &'static str, uint, uint, uint, &'static str)] = &[
+        ("", "", 16, 1, 1,
+         "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+          fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906"),
+        ("password", "NaCl", 1024, 8, 16,
+         "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162\
+          2eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640"),
+        ("pleaseletmein", "SodiumChloride", 16384, 8, 1,
+         "7023bdcb3afd7348461c06cd81fd38ebfda8fbba904f8e3ea9b543f6545da1f2\
+          d5432955613f0fcf62d49705242a9af9e61e85dc0d651e40dfcf017b45575887"),
+    ];
+
+    for &(password, salt, n, r, p, expected) in VECTORS.iter() {
+        let params = ScryptParams::new(n, r, p).unwrap();
+        let mut out = [0u8, ..64];
+        scrypt(password.as_bytes(), salt.as_bytes(), &params, out[mut]);
+        assert_eq!(out[].to_hex()[], expected);
+    }
+}
+
+#[test]
+fn test_scrypt_bad_params() {
+    assert!(ScryptParams::new(0, 1, 1).is_none());
+    assert!(ScryptParams::new(1, 1, 1).is_none());
+    assert!(ScryptParams::new(1000, 1, 1).is_none());
+    assert!(ScryptParams::new(16, 0, 1).is_none());
+    assert!(ScryptParams::new(16, 1, 0).is_none());
+    assert!(ScryptParams::new(16, 1 << 15, 1 << 15).is_none());
+    assert!(ScryptParams::new(16, 8, 1).is_some());
+}
@@ -5,6 +5,7 @@ use std::slice::bytes;
 
 pub const BLOCK_SIZE: uint = 64;
 
+#[deriving(Clone)]
 struct Sha1_ {
     h0: u32,
     h1: u32,
@@ -20,6 +21,12 @@ pub struct Sha1 {
     buf: [u8, ..BLOCK_SIZE],
 }
 
+impl Clone for Sha1 {
+    fn clone(&self) -> Sha1 {
+        Sha1 { s: self.s.clone(), buf: self.buf }
+    }
+}
+
 impl Sha1_ {
     fn compress(&mut self, data: &[u8]) {
         assert!(data.len() >= BLOCK_SIZE);
@@ -128,7 +135,7 @@ impl HashFn for Sha1 {
             } else {
                 let n = min(inlen, BLOCK_SIZE - self.s.curlen);
                 bytes::copy_memory(self.buf[mut self.s.curlen..], data[cur_pos..cur_pos + n]);
-                self.s.curlen = n;
+                self.s.curlen += n;
                 cur_pos += n;
                 inlen -= n;
                 if self.s.curlen == BLOCK_SIZE {
@@ -173,6 +180,10 @@ impl HashFn for Sha1 {
 
         return out;
     }
+
+    fn block_size(_: Option<&Sha1>) -> uint { BLOCK_SIZE }
+
+    fn output_size(_: Option<&Sha1>) -> uint { 20 }
 }
 
 #[test]
@@ -195,3 +206,20 @@ fn test2() {
     let digest = sha.digest();
     assert_eq!(digest[].to_hex()[], "bf6db7112b56812702e99d48a7b1dab62d09b3f6");
 }
+
+#[test]
+fn test_split_updates() {
+    let input = Vec::from_elem(1000, 'A' as u8);
+    let mut sha: Sha1 = HashFn::create();
+    sha.update(input[]);
+    let expected = sha.digest();
+
+    // Partial blocks have to add up, however the input is split.
+    for &size in [1u, 7, 63, 64, 65].iter() {
+        let mut sha: Sha1 = HashFn::create();
+        for chunk in input[].chunks(size) {
+            sha.update(chunk);
+        }
+        assert_eq!(sha.digest(), expected);
+    }
+}
@@ -0,0 +1,638 @@
+use HashFn;
+#[cfg(test)]
+use hex_digest;
+
+use std::cmp::min;
+use std::slice::bytes;
+
+pub const BLOCK_SIZE: uint = 64;
+pub const BLOCK_SIZE_512: uint = 128;
+
+static K256: [u32, ..64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+        ];
+
+static SHA224_IV: [u32, ..8] = [
+    0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939,
+    0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+        ];
+
+static SHA256_IV: [u32, ..8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+        ];
+
+#[deriving(Clone)]
+struct Sha256_ {
+    h: [u32, ..8],
+    curlen: uint,
+    length: u64,
+}
+
+/// The shared SHA-256 machinery; SHA-224 only differs in its initial
+/// values and in how much of the final state it outputs.
+struct Engine256 {
+    s: Sha256_,
+    buf: [u8, ..BLOCK_SIZE],
+}
+
+impl Clone for Engine256 {
+    fn clone(&self) -> Engine256 {
+        Engine256 { s: self.s.clone(), buf: self.buf }
+    }
+}
+
+impl Sha256_ {
+    fn compress(&mut self, data: &[u8]) {
+        assert!(data.len() >= BLOCK_SIZE);
+        let mut w = [0u32, ..64];
+
+        for i in range(0u, 16) {
+            w[i] = get_32h!(data, i);
+        }
+
+        for i in range(16u, 64) {
+            w[i] = sha256_g1!(w[i-2]) + w[i-7] + sha256_g0!(w[i-15]) + w[i-16];
+        }
+
+        let (mut a, mut b, mut c, mut d) = (self.h[0], self.h[1], self.h[2], self.h[3]);
+        let (mut e, mut f, mut g, mut h) = (self.h[4], self.h[5], self.h[6], self.h[7]);
+
+        for i in range(0u, 64) {
+            let t1 = h + sha256_s1!(e) + sha256_ch!(e, f, g) + K256[i] + w[i];
+            let t2 = sha256_s0!(a) + sha256_maj!(a, b, c);
+            h = g;
+            g = f;
+            f = e;
+            e = d + t1;
+            d = c;
+            c = b;
+            b = a;
+            a = t1 + t2;
+        }
+
+        self.h[0] += a;
+        self.h[1] += b;
+        self.h[2] += c;
+        self.h[3] += d;
+        self.h[4] += e;
+        self.h[5] += f;
+        self.h[6] += g;
+        self.h[7] += h;
+    }
+}
+
+impl Engine256 {
+    fn new(iv: &[u32, ..8]) -> Engine256 {
+        Engine256 {
+            s: Sha256_ {
+                h: *iv,
+                curlen: 0,
+                length: 0,
+            },
+            buf: [0, ..BLOCK_SIZE]
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        assert!(self.s.curlen <= self.buf.len());
+        let mut inlen = data.len();
+        let mut cur_pos = 0;
+
+        while inlen > 0 {
+            if self.s.curlen == 0 && inlen >= BLOCK_SIZE {
+                self.s.compress(data.slice_from(cur_pos));
+                self.s.length += (BLOCK_SIZE as u64) * 8;
+                cur_pos += BLOCK_SIZE;
+                inlen -= BLOCK_SIZE;
+            } else {
+                let n = min(inlen, BLOCK_SIZE - self.s.curlen);
+                bytes::copy_memory(self.buf[mut self.s.curlen..], data[cur_pos..cur_pos + n]);
+                self.s.curlen += n;
+                cur_pos += n;
+                inlen -= n;
+                if self.s.curlen == BLOCK_SIZE {
+                    self.s.compress(&self.buf);
+                    self.s.length += (BLOCK_SIZE as u64) * 8;
+                    self.s.curlen = 0;
+                }
+            }
+        }
+    }
+
+    /// Pads and compresses the final block, then writes the first
+    /// `outlen` bytes of the state.
+    fn finish(&mut self, outlen: uint) -> Vec<u8> {
+        assert!(self.s.curlen < self.buf.len());
+
+        self.s.length += (self.s.curlen as u64) * 8;
+        self.buf[self.s.curlen] = 0x80;
+        self.s.curlen += 1;
+
+        if self.s.curlen > 56 {
+            while self.s.curlen < 64 {
+                self.buf[self.s.curlen] = 0;
+                self.s.curlen += 1;
+            }
+            self.s.compress(&self.buf);
+            self.s.curlen = 0;
+        }
+
+        while self.s.curlen < 56 {
+            self.buf[self.s.curlen] = 0;
+            self.s.curlen += 1;
+        }
+
+        store_64h!(self.s.length, self.buf.slice_from_mut(56));
+        self.s.compress(&self.buf);
+
+        let mut out = Vec::from_elem(32, 0u8);
+        for i in range(0u, 8) {
+            store_32h!(self.s.h[i], out.slice_from_mut(4*i));
+        }
+        out.truncate(outlen);
+
+        return out;
+    }
+}
+
+#[deriving(Clone)]
+pub struct Sha224 {
+    e: Engine256,
+}
+
+impl HashFn for Sha224 {
+    fn create() -> Sha224 {
+        Sha224 { e: Engine256::new(&SHA224_IV) }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.e.update(data);
+    }
+
+    fn digest(mut self) -> Vec<u8> {
+        self.e.finish(28)
+    }
+
+    fn block_size(_: Option<&Sha224>) -> uint { BLOCK_SIZE }
+
+    fn output_size(_: Option<&Sha224>) -> uint { 28 }
+}
+
+#[deriving(Clone)]
+pub struct Sha256 {
+    e: Engine256,
+}
+
+impl HashFn for Sha256 {
+    fn create() -> Sha256 {
+        Sha256 { e: Engine256::new(&SHA256_IV) }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.e.update(data);
+    }
+
+    fn digest(mut self) -> Vec<u8> {
+        self.e.finish(32)
+    }
+
+    fn block_size(_: Option<&Sha256>) -> uint { BLOCK_SIZE }
+
+    fn output_size(_: Option<&Sha256>) -> uint { 32 }
+}
+
+static K512: [u64, ..80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+        ];
+
+static SHA384_IV: [u64, ..8] = [
+    0xcbbb9d5dc1059ed8, 0x629a292a367cd507, 0x9159015a3070dd17, 0x152fecd8f70e5939,
+    0x67332667ffc00b31, 0x8eb44a8768581511, 0xdb0c2e0d64f98fa7, 0x47b5481dbefa4fa4,
+        ];
+
+static SHA512_IV: [u64, ..8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+        ];
+
+static SHA512_224_IV: [u64, ..8] = [
+    0x8c3d37c819544da2, 0x73e1996689dcd4d6, 0x1dfab7ae32ff9c82, 0x679dd514582f9fcf,
+    0x0f6d2b697bd44da8, 0x77e36f7304c48942, 0x3f9d85a86a1d36c8, 0x1112e6ad91d692a1,
+        ];
+
+static SHA512_256_IV: [u64, ..8] = [
+    0x22312194fc2bf72c, 0x9f555fa3c84c64c2, 0x2393b86b6f53b151, 0x963877195940eabd,
+    0x96283ee2a88effe3, 0xbe5e1e2553863992, 0x2b0199fc2c85b8aa, 0x0eb72ddc81c52ca2,
+        ];
+
+#[deriving(Clone)]
+struct Sha512_ {
+    h: [u64, ..8],
+    curlen: uint,
+    length: u64,
+}
+
+/// The SHA-512 counterpart of `Engine256`, shared by SHA-384, SHA-512 and
+/// the truncated SHA-512/t variants.
+struct Engine512 {
+    s: Sha512_,
+    buf: [u8, ..BLOCK_SIZE_512],
+}
+
+impl Clone for Engine512 {
+    fn clone(&self) -> Engine512 {
+        Engine512 { s: self.s.clone(), buf: self.buf }
+    }
+}
+
+impl Sha512_ {
+    fn compress(&mut self, data: &[u8]) {
+        assert!(data.len() >= BLOCK_SIZE_512);
+        let mut w = [0u64, ..80];
+
+        for i in range(0u, 16) {
+            w[i] = get_64h!(data, i);
+        }
+
+        for i in range(16u, 80) {
+            w[i] = sha512_g1!(w[i-2]) + w[i-7] + sha512_g0!(w[i-15]) + w[i-16];
+        }
+
+        let (mut a, mut b, mut c, mut d) = (self.h[0], self.h[1], self.h[2], self.h[3]);
+        let (mut e, mut f, mut g, mut h) = (self.h[4], self.h[5], self.h[6], self.h[7]);
+
+        for i in range(0u, 80) {
+            let t1 = h + sha512_s1!(e) + sha256_ch!(e, f, g) + K512[i] + w[i];
+            let t2 = sha512_s0!(a) + sha256_maj!(a, b, c);
+            h = g;
+            g = f;
+            f = e;
+            e = d + t1;
+            d = c;
+            c = b;
+            b = a;
+            a = t1 + t2;
+        }
+
+        self.h[0] += a;
+        self.h[1] += b;
+        self.h[2] += c;
+        self.h[3] += d;
+        self.h[4] += e;
+        self.h[5] += f;
+        self.h[6] += g;
+        self.h[7] += h;
+    }
+}
+
+impl Engine512 {
+    fn new(iv: &[u64, ..8]) -> Engine512 {
+        Engine512 {
+            s: Sha512_ {
+                h: *iv,
+                curlen: 0,
+                length: 0,
+            },
+            buf: [0, ..BLOCK_SIZE_512]
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        assert!(self.s.curlen <= self.buf.len());
+        let mut inlen = data.len();
+        let mut cur_pos = 0;
+
+        while inlen > 0 {
+            if self.s.curlen == 0 && inlen >= BLOCK_SIZE_512 {
+                self.s.compress(data.slice_from(cur_pos));
+                self.s.length += (BLOCK_SIZE_512 as u64) * 8;
+                cur_pos += BLOCK_SIZE_512;
+                inlen -= BLOCK_SIZE_512;
+            } else {
+                let n = min(inlen, BLOCK_SIZE_512 - self.s.curlen);
+                bytes::copy_memory(self.buf[mut self.s.curlen..], data[cur_pos..cur_pos + n]);
+                self.s.curlen += n;
+                cur_pos += n;
+                inlen -= n;
+                if self.s.curlen == BLOCK_SIZE_512 {
+                    self.s.compress(&self.buf);
+                    self.s.length += (BLOCK_SIZE_512 as u64) * 8;
+                    self.s.curlen = 0;
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self, outlen: uint) -> Vec<u8> {
+        assert!(self.s.curlen < self.buf.len());
+
+        self.s.length += (self.s.curlen as u64) * 8;
+        self.buf[self.s.curlen] = 0x80;
+        self.s.curlen += 1;
+
+        if self.s.curlen > 112 {
+            while self.s.curlen < 128 {
+                self.buf[self.s.curlen] = 0;
+                self.s.curlen += 1;
+            }
+            self.s.compress(&self.buf);
+            self.s.curlen = 0;
+        }
+
+        // The length field is 128 bits wide, but we only count up to 2^64
+        // bits, so the upper half is always zero.
+        while self.s.curlen < 120 {
+            self.buf[self.s.curlen] = 0;
+            self.s.curlen += 1;
+        }
+
+        store_64h!(self.s.length, self.buf.slice_from_mut(120));
+        self.s.compress(&self.buf);
+
+        let mut out = Vec::from_elem(64, 0u8);
+        for i in range(0u, 8) {
+            store_64h!(self.s.h[i], out.slice_from_mut(8*i));
+        }
+        out.truncate(outlen);
+
+        return out;
+    }
+}
+
+#[deriving(Clone)]
+pub struct Sha384 {
+    e: Engine512,
+}
+
+impl HashFn for Sha384 {
+    fn create() -> Sha384 {
+        Sha384 { e: Engine512::new(&SHA384_IV) }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.e.update(data);
+    }
+
+    fn digest(mut self) -> Vec<u8> {
+        self.e.finish(48)
+    }
+
+    fn block_size(_: Option<&Sha384>) -> uint { BLOCK_SIZE_512 }
+
+    fn output_size(_: Option<&Sha384>) -> uint { 48 }
+}
+
+#[deriving(Clone)]
+pub struct Sha512 {
+    e: Engine512,
+}
+
+impl HashFn for Sha512 {
+    fn create() -> Sha512 {
+        Sha512 { e: Engine512::new(&SHA512_IV) }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.e.update(data);
+    }
+
+    fn digest(mut self) -> Vec<u8> {
+        self.e.finish(64)
+    }
+
+    fn block_size(_: Option<&Sha512>) -> uint { BLOCK_SIZE_512 }
+
+    fn output_size(_: Option<&Sha512>) -> uint { 64 }
+}
+
+#[allow(non_camel_case_types)]
+#[deriving(Clone)]
+pub struct Sha512_224 {
+    e: Engine512,
+}
+
+impl HashFn for Sha512_224 {
+    fn create() -> Sha512_224 {
+        Sha512_224 { e: Engine512::new(&SHA512_224_IV) }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.e.update(data);
+    }
+
+    fn digest(mut self) -> Vec<u8> {
+        self.e.finish(28)
+    }
+
+    fn block_size(_: Option<&Sha512_224>) -> uint { BLOCK_SIZE_512 }
+
+    fn output_size(_: Option<&Sha512_224>) -> uint { 28 }
+}
+
+#[allow(non_camel_case_types)]
+#[deriving(Clone)]
+pub struct Sha512_256 {
+    e: Engine512,
+}
+
+impl HashFn for Sha512_256 {
+    fn create() -> Sha512_256 {
+        Sha512_256 { e: Engine512::new(&SHA512_256_IV) }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.e.update(data);
+    }
+
+    fn digest(mut self) -> Vec<u8> {
+        self.e.finish(32)
+    }
+
+    fn block_size(_: Option<&Sha512_256>) -> uint { BLOCK_SIZE_512 }
+
+    fn output_size(_: Option<&Sha512_256>) -> uint { 32 }
+}
+
+#[test]
+fn test_sha256_shavs() {
+    use serialize::hex::FromHex;
+    // (Msg, MD) pairs from SHA256ShortMsg.rsp
+    static VECTORS: &'static [(&'static str, &'static str)] = &[
+        ("", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+        ("d3", "28969cdfa74a12c82f3bad960b0b000aca2ac329deea5c2328ebc6f2ba9802c1"),
+        ("11af", "5ca7133fa735326081558ac312c620eeca9970d1e70a4b95533d956f072d1f98"),
+        ("b4190e", "dff2e73091f6c05e528896c4c831b9448653dc2ff043528f6769437bc7b975c2"),
+        ("74ba2521", "b16aa56be3880d18cd41e68384cf1ec8c17680c45a02b1575dc1518923ae8b0e"),
+        ("c299209682", "f0887fe961c9cd3beab957e8222494abb969b1ce4c6557976df8b0f6d20e9166"),
+        ("e1dc724d5621", "eca0a060b489636225b4fa64d267dabbe44273067ac679f20820bddc6b6a90ac"),
+        ("06e076f5a442d5", "3fd877e27450e6bbd5d74bb82f9870c64c66e109418baa8e6bbcff355e287926"),
+        ("5738c929c4f4ccb6", "963bb88f27f512777aab6c8b1a02c70ec0ad651d428f870036e1917120fb48bf"),
+    ];
+
+    for &(msg, md) in VECTORS.iter() {
+        assert_eq!(hex_digest::<Sha256>(msg.from_hex().unwrap()[])[], md);
+    }
+}
+
+#[test]
+fn test_sha224_shavs() {
+    use serialize::hex::FromHex;
+    // (Msg, MD) pairs from SHA224ShortMsg.rsp
+    static VECTORS: &'static [(&'static str, &'static str)] = &[
+        ("", "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f"),
+        ("d3", "244eeeb91219c6e02a6fd45f19e21fe4d7a4696e32e7e4f292ecf177"),
+        ("11af", "e35cfb635c524ebb2bc494ea26773e7b359118bc4a0866f390377e0c"),
+        ("b4190e", "babc27de531c0a27e94686281f540d7ad66101b92675b01b352d4492"),
+        ("74ba2521", "4d692cabcefaead680adfff19f1418f1e78bc70a152b64543775166c"),
+        ("c299209682", "50daabcb6e012f33f204b40ba34a0f1ab1af462b8c41b1d9a1bd52f0"),
+        ("e1dc724d5621", "0a5004ea5b0ebcef5774567710bbdc248acc3fc7af7520d204217ed1"),
+        ("06e076f5a442d5", "15f241d06023f716a6632ddb949bda64655c1024ecaeca475735a96a"),
+        ("5738c929c4f4ccb6", "32a7350c6e9948eb65a62e1e58ef23fed777c79e769eab02d2252f83"),
+    ];
+
+    for &(msg, md) in VECTORS.iter() {
+        assert_eq!(hex_digest::<Sha224>(msg.from_hex().unwrap()[])[], md);
+    }
+}
+
+#[test]
+fn test_sha256_fips180() {
+    assert_eq!(hex_digest::<Sha256>(b"abc")[],
+               "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(hex_digest::<Sha256>(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")[],
+               "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    assert_eq!(hex_digest::<Sha224>(b"abc")[],
+               "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7");
+    assert_eq!(hex_digest::<Sha224>(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")[],
+               "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525");
+}
+
+#[test]
+fn test_sha256_million_a() {
+    use serialize::hex::ToHex;
+    let input = Vec::from_elem(1000, 'a' as u8);
+    let mut sha: Sha256 = HashFn::create();
+    for _ in range(0u, 1000) {
+        sha.update(input[]);
+    }
+    assert_eq!(sha.digest()[].to_hex()[],
+               "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+}
+
+#[test]
+fn test_sha512_shavs() {
+    use serialize::hex::FromHex;
+    // (Msg, MD) pairs from SHA512ShortMsg.rsp
+    static VECTORS: &'static [(&'static str, &'static str)] = &[
+        ("", "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"),
+        ("21", "3831a6a6155e509dee59a7f451eb35324d8f8f2df6e3708894740f98fdee23889f4de5adb0c5010dfb555cda77c8ab5dc902094c52de3278f35a75ebc25f093a"),
+        ("9083", "55586ebba48768aeb323655ab6f4298fc9f670964fc2e5f2731e34dfa4b0c09e6e1e12e3d7286b3145c61c2047fb1a2a1297f36da64160b31fa4c8c2cddd2fb4"),
+        ("0a55db", "7952585e5330cb247d72bae696fc8a6b0f7d0804577e347d99bc1b11e52f384985a428449382306a89261ae143c2f3fb613804ab20b42dc097e5bf4a96ef919b"),
+        ("23be86d5", "76d42c8eadea35a69990c63a762f330614a4699977f058adb988f406fb0be8f2ea3dce3a2bbd1d827b70b9b299ae6f9e5058ee97b50bd4922d6d37ddc761f8eb"),
+        ("eb0ca946c1", "d39ecedfe6e705a821aee4f58bfc489c3d9433eb4ac1b03a97e321a2586b40dd0522f40fa5aef36afff591a78c916bfc6d1ca515c4983dd8695b1ec7951d723e"),
+    ];
+
+    for &(msg, md) in VECTORS.iter() {
+        assert_eq!(hex_digest::<Sha512>(msg.from_hex().unwrap()[])[], md);
+    }
+}
+
+#[test]
+fn test_sha384_shavs() {
+    use serialize::hex::FromHex;
+    // (Msg, MD) pairs from SHA384ShortMsg.rsp
+    static VECTORS: &'static [(&'static str, &'static str)] = &[
+        ("", "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b"),
+        ("21", "1d0ec8c84ee9521e21f06774de232367b64de628474cb5b2e372b699a1f55ae335cc37193ef823e33324dfd9a70738a6"),
+        ("9083", "a4944036e613768be2e8875c9df2d797bc039b6325c3be8c5959dccae3b1f91b9f5580991831cf5860581eafc520d0a7"),
+        ("0a55db", "1e59d58ffaabf93621a9d849a983e6e612772d5603bae8b4ff73299e0879ac69b317f163770a9e8624b5b6a9894f9d17"),
+        ("23be86d5", "00bddc23d3d763a7104ef25bcebacf118e8176574402d45165e68a93ea9494d8d5ac014c8fa2ecc44e2882d9fce51652"),
+        ("eb0ca946c1", "4354f63a72dfe12f048136f20524dc3658ea0257e82528866c83b05b322472e879765d8425f771a8261d2e5c26466a6b"),
+    ];
+
+    for &(msg, md) in VECTORS.iter() {
+        assert_eq!(hex_digest::<Sha384>(msg.from_hex().unwrap()[])[], md);
+    }
+}
+
+#[test]
+fn test_sha512_t_shavs() {
+    use serialize::hex::FromHex;
+    // (Msg, SHA-512/224 MD, SHA-512/256 MD)
+    static VECTORS: &'static [(&'static str, &'static str, &'static str)] = &[
+        ("", "6ed0dd02806fa89e25de060c19d3ac86cabb87d6a0ddd05c333b84f4",
+         "c672b8d1ef56ed28ab87c3622c5114069bdd3ad7b8f9737498d0c01ecef0967a"),
+        ("21", "dcdcb6e45528d990b8f45e4157309539f769fe1ed1287186b864ffc2",
+         "9c732ed33639f0936f85115879b34846f3ddd7710092d0e5bf7964074902afbd"),
+        ("9083", "9e67a2e8470d16378d46470440406621a06f84adf482e5d05cda14a3",
+         "d53d3abd674297baeed2e2765896442685cabb0fbcf52f05cbf238f3f69755a9"),
+        ("0a55db", "f9c024cee76a5c955dc1c6bf2993e3444438e4b0b2b84fa861f72761",
+         "77aaf9abe8499cb4c638e6e3ec1ed8d03c5eee456c633060c722c5b9040d4fba"),
+        ("23be86d5", "f4d815f729041179c6c7c5003900b095609fcba8a23790952afd6874",
+         "7122ff1d4629a3db7f47986e47a37d5a968365e9f56bf119d8475d732ce4ede2"),
+        ("eb0ca946c1", "f0096afe82ef47b4a969602dc88cdec8e77161b1c917dda8b79d42a6",
+         "62cc350eba00e49a1b756cfef6cb2631739a442a1230ba7aa7e2d2e977cce17e"),
+    ];
+
+    for &(msg, md224, md256) in VECTORS.iter() {
+        let msg = msg.from_hex().unwrap();
+        assert_eq!(hex_digest::<Sha512_224>(msg[])[], md224);
+        assert_eq!(hex_digest::<Sha512_256>(msg[])[], md256);
+    }
+}
+
+#[test]
+fn test_sha512_fips180() {
+    static TWO_BLOCK: &'static [u8] = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+    assert_eq!(hex_digest::<Sha512>(b"abc")[],
+               "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f");
+    assert_eq!(hex_digest::<Sha512>(TWO_BLOCK)[],
+               "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909");
+    assert_eq!(hex_digest::<Sha384>(b"abc")[],
+               "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7");
+    assert_eq!(hex_digest::<Sha384>(TWO_BLOCK)[],
+               "09330c33f71147e83d192fc782cd1b4753111b173b3b05d22fa08086e3b0f712fcc7c71a557e2db966c3e9fa91746039");
+    assert_eq!(hex_digest::<Sha512_224>(TWO_BLOCK)[],
+               "23fec5bb94d60b23308192640b0c453335d664734fe40e7268674af9");
+    assert_eq!(hex_digest::<Sha512_256>(TWO_BLOCK)[],
+               "3928e184fb8690f840da3988121d31be65cb9d3ef83ee6146feac861e19b563a");
+}
+
+#[test]
+fn test_sha512_million_a() {
+    use serialize::hex::ToHex;
+    let input = Vec::from_elem(1000, 'a' as u8);
+    let mut sha: Sha512 = HashFn::create();
+    for _ in range(0u, 1000) {
+        sha.update(input[]);
+    }
+    assert_eq!(sha.digest()[].to_hex()[],
+               "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973ebde0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b");
+}
@@ -0,0 +1,281 @@
+use {HashFn, ExtendableOutputFn, XofReader};
+#[cfg(test)]
+use hex_digest;
+
+static RC: [u64, ..24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+        ];
+
+static ROTC: [uint, ..24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14,
+    27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+        ];
+
+static PILN: [uint, ..24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4,
+    15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+        ];
+
+/// SHA-3 domain separation suffix, including the first padding bit.
+const SHA3_DS: u8 = 0x06;
+/// SHAKE domain separation suffix, including the first padding bit.
+const SHAKE_DS: u8 = 0x1f;
+
+fn keccak_f(st: &mut [u64, ..25]) {
+    let mut bc = [0u64, ..5];
+
+    for round in range(0u, 24) {
+        // theta
+        for i in range(0u, 5) {
+            bc[i] = st[i] ^ st[i + 5] ^ st[i + 10] ^ st[i + 15] ^ st[i + 20];
+        }
+        for i in range(0u, 5) {
+            let t = bc[(i + 4) % 5] ^ rotl!(bc[(i + 1) % 5], 1);
+            for j in range(0u, 5) {
+                st[5*j + i] ^= t;
+            }
+        }
+
+        // rho and pi
+        let mut t = st[1];
+        for i in range(0u, 24) {
+            let j = PILN[i];
+            let tmp = st[j];
+            st[j] = rotl!(t, ROTC[i]);
+            t = tmp;
+        }
+
+        // chi
+        for j in range(0u, 5) {
+            for i in range(0u, 5) {
+                bc[i] = st[5*j + i];
+            }
+            for i in range(0u, 5) {
+                st[5*j + i] ^= !bc[(i + 1) % 5] & bc[(i + 2) % 5];
+            }
+        }
+
+        // iota
+        st[0] ^= RC[round];
+    }
+}
+
+/// A Keccak-f[1600] sponge. Bytes are XORed into (and read out of) the
+/// lanes little-endian, one at a time, so this doesn't care about host
+/// byte order.
+#[deriving(Clone)]
+struct Sponge {
+    st: [u64, ..25],
+    rate: uint,
+    pos: uint,
+}
+
+impl Sponge {
+    fn new(rate: uint) -> Sponge {
+        assert!(rate > 0 && rate < 200);
+        Sponge {
+            st: [0, ..25],
+            rate: rate,
+            pos: 0,
+        }
+    }
+
+    fn xor_byte(&mut self, pos: uint, b: u8) {
+        self.st[pos / 8] ^= (b as u64) << (8 * (pos % 8));
+    }
+
+    fn absorb(&mut self, data: &[u8]) {
+        for &b in data.iter() {
+            let pos = self.pos;
+            self.xor_byte(pos, b);
+            self.pos += 1;
+            if self.pos == self.rate {
+                keccak_f(&mut self.st);
+                self.pos = 0;
+            }
+        }
+    }
+
+    /// Applies the domain suffix and pad10*1, and switches to squeezing.
+    fn pad(&mut self, ds: u8) {
+        let (pos, last) = (self.pos, self.rate - 1);
+        self.xor_byte(pos, ds);
+        self.xor_byte(last, 0x80);
+        keccak_f(&mut self.st);
+        self.pos = 0;
+    }
+
+    fn squeeze(&mut self, out: &mut [u8]) {
+        for o in out.iter_mut() {
+            if self.pos == self.rate {
+                keccak_f(&mut self.st);
+                self.pos = 0;
+            }
+            *o = (self.st[self.pos / 8] >> (8 * (self.pos % 8))) as u8;
+            self.pos += 1;
+        }
+    }
+}
+
+macro_rules! sha3_impl (
+    ($name: ident, $outlen: expr) => (
+        #[allow(non_camel_case_types)]
+        #[deriving(Clone)]
+        pub struct $name {
+            sponge: Sponge,
+        }
+
+        impl HashFn for $name {
+            fn create() -> $name {
+                $name { sponge: Sponge::new(200 - 2 * $outlen) }
+            }
+
+            fn update(&mut self, data: &[u8]) {
+                self.sponge.absorb(data);
+            }
+
+            fn digest(mut self) -> Vec<u8> {
+                let mut out = Vec::from_elem($outlen, 0u8);
+                self.sponge.pad(SHA3_DS);
+                self.sponge.squeeze(out[mut]);
+                return out;
+            }
+
+            fn block_size(_: Option<&$name>) -> uint { 200 - 2 * $outlen }
+
+            fn output_size(_: Option<&$name>) -> uint { $outlen }
+        }
+    )
+)
+
+sha3_impl!(Sha3_224, 28)
+sha3_impl!(Sha3_256, 32)
+sha3_impl!(Sha3_384, 48)
+sha3_impl!(Sha3_512, 64)
+
+/// The squeezing half of a SHAKE instance.
+#[deriving(Clone)]
+pub struct ShakeReader {
+    sponge: Sponge,
+}
+
+impl XofReader for ShakeReader {
+    fn read(&mut self, out: &mut [u8]) {
+        self.sponge.squeeze(out);
+    }
+}
+
+macro_rules! shake_impl (
+    ($name: ident, $security: expr) => (
+        #[deriving(Clone)]
+        pub struct $name {
+            sponge: Sponge,
+        }
+
+        impl ExtendableOutputFn<ShakeReader> for $name {
+            fn create() -> $name {
+                $name { sponge: Sponge::new(200 - $security / 4) }
+            }
+
+            fn update(&mut self, data: &[u8]) {
+                self.sponge.absorb(data);
+            }
+
+            fn finalize(mut self) -> ShakeReader {
+                self.sponge.pad(SHAKE_DS);
+                ShakeReader { sponge: self.sponge }
+            }
+        }
+    )
+)
+
+shake_impl!(Shake128, 128)
+shake_impl!(Shake256, 256)
+
+#[cfg(test)]
+fn hex_xof<R: XofReader, X: ExtendableOutputFn<R>>(input: &[u8], len: uint) -> String {
+    use serialize::hex::ToHex;
+    let mut x: X = ExtendableOutputFn::create();
+    x.update(input);
+    let mut reader = x.finalize();
+    let mut out = Vec::from_elem(len, 0u8);
+    reader.read(out[mut]);
+    out[].to_hex()
+}
+
+#[test]
+fn test_sha3_empty() {
+    assert_eq!(hex_digest::<Sha3_224>(b"")[],
+               "6b4e03423667dbb73b6e15454f0eb1abd4597f9a1b078e3f5b5a6bc7");
+    assert_eq!(hex_digest::<Sha3_256>(b"")[],
+               "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a");
+    assert_eq!(hex_digest::<Sha3_384>(b"")[],
+               "0c63a75b845e4f7d01107d852e4c2485c51a50aaaa94fc61995e71bbee983a2ac3713831264adb47fb6bd1e058d5f004");
+    assert_eq!(hex_digest::<Sha3_512>(b"")[],
+               "a69f73cca23a9ac5c8b567dc185a756e97c982164fe25859e0d1dcc1475c80a615b2123af1f5f94c11e3e9402c3ac558f500199d95b6d3e301758586281dcd26");
+}
+
+#[test]
+fn test_sha3_abc() {
+    assert_eq!(hex_digest::<Sha3_224>(b"abc")[],
+               "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf");
+    assert_eq!(hex_digest::<Sha3_256>(b"abc")[],
+               "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532");
+    assert_eq!(hex_digest::<Sha3_384>(b"abc")[],
+               "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0e49be4b298d88cea927ac7f539f1edf228376d25");
+    assert_eq!(hex_digest::<Sha3_512>(b"abc")[],
+               "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0");
+}
+
+#[test]
+fn test_sha3_1600_bits() {
+    // The 200 x 0xa3 message from the NIST SHA-3 examples, which spans more
+    // than one block for every rate.
+    let input = Vec::from_elem(200, 0xa3u8);
+    assert_eq!(hex_digest::<Sha3_224>(input[])[],
+               "9376816aba503f72f96ce7eb65ac095deee3be4bf9bbc2a1cb7e11e0");
+    assert_eq!(hex_digest::<Sha3_256>(input[])[],
+               "79f38adec5c20307a98ef76e8324afbfd46cfd81b22e3973c65fa1bd9de31787");
+    assert_eq!(hex_digest::<Sha3_384>(input[])[],
+               "1881de2ca7e41ef95dc4732b8f5f002b189cc1e42b74168ed1732649ce1dbcdd76197a31fd55ee989f2d7050dd473e8f");
+    assert_eq!(hex_digest::<Sha3_512>(input[])[],
+               "e76dfad22084a8b1467fcf2ffa58361bec7628edf5f3fdc0e4805dc48caeeca81b7c13c30adf52a3659584739a2df46be589c51ca1a4a8416df6545a1ce8ba00");
+}
+
+#[test]
+fn test_shake() {
+    let input = Vec::from_elem(200, 0xa3u8);
+    assert_eq!(hex_xof::<ShakeReader, Shake128>(b"", 32)[],
+               "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26");
+    assert_eq!(hex_xof::<ShakeReader, Shake256>(b"", 64)[],
+               "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762fd75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be");
+    assert_eq!(hex_xof::<ShakeReader, Shake128>(input[], 32)[],
+               "131ab8d2b594946b9c81333f9bb6e0ce75c3b93104fa3469d3917457385da037");
+    assert_eq!(hex_xof::<ShakeReader, Shake256>(input[], 64)[],
+               "cd8a920ed141aa0407a22d59288652e9d9f1a7ee0c1e7c1ca699424da84a904d2d700caae7396ece96604440577da4f3aa22aeb8857f961c4cd8e06f0ae6610b");
+}
+
+#[test]
+fn test_shake_incremental_squeeze() {
+    use serialize::hex::ToHex;
+    // Squeezing in odd-sized pieces has to give the same stream as one big
+    // read; this crosses several SHAKE128 rate boundaries.
+    let input = Vec::from_elem(200, 0xa3u8);
+    let mut shake: Shake128 = ExtendableOutputFn::create();
+    shake.update(input[]);
+    let mut reader = shake.finalize();
+    let mut out = Vec::from_elem(512, 0u8);
+    let mut pos = 0;
+    while pos < out.len() {
+        let end = ::std::cmp::min(pos + 37, out.len());
+        reader.read(out[mut pos..end]);
+        pos = end;
+    }
+    assert_eq!(out[480..].to_hex()[],
+               "44c9fb359fd56ac0a9a75a743cff6862f17d7259ab075216c0699511643b6439");
+}
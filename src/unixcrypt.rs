@@ -0,0 +1,311 @@
+use HashFn;
+use constanttime::ConstantTimeEq;
+use md5::Md5;
+use securemem::SecureMem;
+use sha2::{Sha256, Sha512};
+
+use std::cmp::{max, min};
+use std::from_str::from_str;
+
+/// crypt(3)'s base64 alphabet, which is ordered differently from both
+/// standard base64 and bcrypt's.
+static ALPHABET: &'static [u8] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+const MD5_SALT_MAX: uint = 8;
+const SHA_SALT_MAX: uint = 16;
+const ROUNDS_DEFAULT: uint = 5000;
+const ROUNDS_MIN: uint = 1000;
+const ROUNDS_MAX: uint = 999999999;
+
+/// Which digest bytes go into each group of four output characters, most
+/// significant first. The bytes left over are encoded separately.
+static MD5_ORDER: [(uint, uint, uint), ..5] = [
+    (0, 6, 12), (1, 7, 13), (2, 8, 14), (3, 9, 15), (4, 10, 5),
+];
+
+static SHA256_ORDER: [(uint, uint, uint), ..10] = [
+    (0, 10, 20), (21, 1, 11), (12, 22, 2), (3, 13, 23), (24, 4, 14),
+    (15, 25, 5), (6, 16, 26), (27, 7, 17), (18, 28, 8), (9, 19, 29),
+];
+
+static SHA512_ORDER: [(uint, uint, uint), ..21] = [
+    (0, 21, 42), (22, 43, 1), (44, 2, 23), (3, 24, 45), (25, 46, 4),
+    (47, 5, 26), (6, 27, 48), (28, 49, 7), (50, 8, 29), (9, 30, 51),
+    (31, 52, 10), (53, 11, 32), (12, 33, 54), (34, 55, 13), (56, 14, 35),
+    (15, 36, 57), (37, 58, 16), (59, 17, 38), (18, 39, 60), (40, 61, 19),
+    (62, 20, 41),
+];
+
+/// Appends `n` characters for the 24-bit group `b2 b1 b0`, least
+/// significant six bits first.
+fn b64_from_24bit(out: &mut String, b2: u8, b1: u8, b0: u8, n: uint) {
+    let mut w = (b2 as uint << 16) | (b1 as uint << 8) | b0 as uint;
+    for _ in range(0, n) {
+        out.push(ALPHABET[w & 0x3f] as char);
+        w >>= 6;
+    }
+}
+
+fn encode(out: &mut String, digest: &[u8], order: &[(uint, uint, uint)]) {
+    for &(a, b, c) in order.iter() {
+        b64_from_24bit(out, digest[a], digest[b], digest[c], 4);
+    }
+    match digest.len() {
+        16 => b64_from_24bit(out, 0, 0, digest[11], 2),
+        32 => b64_from_24bit(out, 0, digest[31], digest[30], 3),
+        _ => b64_from_24bit(out, 0, 0, digest[63], 2),
+    }
+}
+
+/// The salt is whatever comes before the next `$`, cut to at most `max`
+/// bytes.
+fn salt_part(salt: &str, max: uint) -> &str {
+    let salt = match salt.find('$') {
+        Some(end) => salt[..end],
+        None => salt,
+    };
+    let mut n = min(salt.len(), max);
+    while !salt.is_char_boundary(n) {
+        n -= 1;
+    }
+    salt[..n]
+}
+
+/// `digest` repeated and cut to `len` bytes.
+fn repeat_digest(digest: Vec<u8>, len: uint) -> SecureMem<Vec<u8>> {
+    let digest = SecureMem::new(digest);
+    let mut out = SecureMem::new(Vec::with_capacity(len));
+    for i in range(0, len) {
+        out.push(digest.as_slice()[i % digest.len()]);
+    }
+    out
+}
+
+/// MD5-crypt, `$1$`. At most 8 bytes of salt are used.
+pub fn md5_crypt(password: &[u8], salt: &str) -> String {
+    let salt = salt_part(salt, MD5_SALT_MAX);
+
+    let mut h: Md5 = HashFn::create();
+    h.update(password);
+    h.update(salt.as_bytes());
+    h.update(password);
+    let mut fin = SecureMem::new(h.digest());
+
+    let mut h: Md5 = HashFn::create();
+    h.update(password);
+    h.update(b"$1$");
+    h.update(salt.as_bytes());
+    let mut left = password.len();
+    while left > 0 {
+        h.update(fin.slice_to(min(left, 16)));
+        left -= min(left, 16);
+    }
+    // This is meant to mix in the length, but the reference implementation
+    // used the wrong buffer, and that's what everyone has to match.
+    let mut i = password.len();
+    while i > 0 {
+        if i & 1 == 1 {
+            h.update(&[0u8]);
+        } else {
+            h.update(password[..1]);
+        }
+        i >>= 1;
+    }
+    fin = SecureMem::new(h.digest());
+
+    for i in range(0u, 1000) {
+        let mut h: Md5 = HashFn::create();
+        if i & 1 == 1 { h.update(password) } else { h.update(fin.as_slice()) }
+        if i % 3 != 0 { h.update(salt.as_bytes()) }
+        if i % 7 != 0 { h.update(password) }
+        if i & 1 == 1 { h.update(fin.as_slice()) } else { h.update(password) }
+        fin = SecureMem::new(h.digest());
+    }
+
+    let mut out = String::from_str("$1$");
+    out.push_str(salt);
+    out.push('$');
+    encode(&mut out, fin.as_slice(), &MD5_ORDER);
+    out
+}
+
+/// The digest at the core of SHA-crypt, shared by `$5$` and `$6$`.
+fn sha_crypt_digest<H: HashFn>(password: &[u8], salt: &[u8], rounds: uint) -> SecureMem<Vec<u8>> {
+    let mut h: H = HashFn::create();
+    h.update(password);
+    h.update(salt);
+    h.update(password);
+    let b = SecureMem::new(h.digest());
+    let n = b.len();
+
+    let mut h: H = HashFn::create();
+    h.update(password);
+    h.update(salt);
+    let mut left = password.len();
+    while left > n {
+        h.update(b.as_slice());
+        left -= n;
+    }
+    h.update(b.slice_to(left));
+    let mut i = password.len();
+    while i > 0 {
+        if i & 1 == 1 { h.update(b.as_slice()) } else { h.update(password) }
+        i >>= 1;
+    }
+    let a = SecureMem::new(h.digest());
+
+    let mut h: H = HashFn::create();
+    for _ in range(0, password.len()) {
+        h.update(password);
+    }
+    let p = repeat_digest(h.digest(), password.len());
+
+    let mut h: H = HashFn::create();
+    for _ in range(0, 16 + a.as_slice()[0] as uint) {
+        h.update(salt);
+    }
+    let s = repeat_digest(h.digest(), salt.len());
+
+    let mut c = a;
+    for i in range(0, rounds) {
+        let mut h: H = HashFn::create();
+        if i & 1 == 1 { h.update(p.as_slice()) } else { h.update(c.as_slice()) }
+        if i % 3 != 0 { h.update(s.as_slice()) }
+        if i % 7 != 0 { h.update(p.as_slice()) }
+        if i & 1 == 1 { h.update(c.as_slice()) } else { h.update(p.as_slice()) }
+        c = SecureMem::new(h.digest());
+    }
+    c
+}
+
+fn sha_crypt<H: HashFn>(magic: &str, order: &[(uint, uint, uint)], password: &[u8],
+                        salt: &str, rounds: Option<uint>) -> String {
+    let salt = salt_part(salt, SHA_SALT_MAX);
+    let mut out = String::from_str(magic);
+    let rounds = match rounds {
+        Some(r) => {
+            // Out-of-range round counts are clamped rather than rejected,
+            // and the string records the count actually used.
+            let r = min(max(r, ROUNDS_MIN), ROUNDS_MAX);
+            out.push_str(format!("rounds={}$", r)[]);
+            r
+        }
+        None => ROUNDS_DEFAULT,
+    };
+    let c = sha_crypt_digest::<H>(password, salt.as_bytes(), rounds);
+
+    out.push_str(salt);
+    out.push('$');
+    encode(&mut out, c.as_slice(), order);
+    out
+}
+
+/// SHA-256-crypt, `$5$`. At most 16 bytes of salt are used, and `None`
+/// means the default 5000 rounds, which then aren't written out.
+pub fn sha256_crypt(password: &[u8], salt: &str, rounds: Option<uint>) -> String {
+    sha_crypt::<Sha256>("$5$", &SHA256_ORDER, password, salt, rounds)
+}
+
+/// SHA-512-crypt, `$6$`, with the same salt and rounds handling as
+/// `sha256_crypt`.
+pub fn sha512_crypt(password: &[u8], salt: &str, rounds: Option<uint>) -> String {
+    sha_crypt::<Sha512>("$6$", &SHA512_ORDER, password, salt, rounds)
+}
+
+/// Hashes `password` the way crypt(3) would with `setting`, which is
+/// either just the prefix and salt (`$6$rounds=10000$salt`) or a whole
+/// existing hash. Returns None for an unsupported or malformed setting.
+pub fn crypt(password: &[u8], setting: &str) -> Option<String> {
+    if setting.starts_with("$1$") {
+        return Some(md5_crypt(password, setting[3..]));
+    }
+    if !setting.starts_with("$5$") && !setting.starts_with("$6$") {
+        return None;
+    }
+
+    let mut rest = setting[3..];
+    let mut rounds = None;
+    if rest.starts_with("rounds=") {
+        let end = match rest.find('$') {
+            Some(end) => end,
+            None => return None,
+        };
+        rounds = match from_str::<uint>(rest[7..end]) {
+            Some(r) => Some(r),
+            None => return None,
+        };
+        rest = rest[end + 1..];
+    }
+
+    if setting.starts_with("$5$") {
+        Some(sha256_crypt(password, rest, rounds))
+    } else {
+        Some(sha512_crypt(password, rest, rounds))
+    }
+}
+
+/// Checks `password` against a `$1$`, `$5$` or `$6$` hash, e.g. from
+/// /etc/shadow.
+pub fn verify(password: &[u8], hashed: &str) -> bool {
+    match crypt(password, hashed) {
+        Some(h) => h.as_bytes().const_time_eq(&hashed.as_bytes()),
+        None => false,
+    }
+}
+
+#[test]
+fn test_md5_crypt() {
+    assert_eq!(md5_crypt(b"Hello world!", "saltstring")[],
+               "$1$saltstri$YMyguxXMBpd2TEZ.vS/3q1");
+    assert_eq!(md5_crypt(b"", "saltstring")[],
+               "$1$saltstri$ciR2otLVXV8I9sOPWbLTc1");
+    assert_eq!(md5_crypt(b"password", "")[],
+               "$1$$I2o9Z7NcvQAKp7wyCTlia0");
+    assert_eq!(md5_crypt(b"password", "abcdefghijk")[],
+               "$1$abcdefgh$G//4keteveJp0qb8z2DxG/");
+}
+
+#[test]
+fn test_sha256_crypt() {
+    // From the SHA-crypt specification, plus glibc for the rest.
+    assert_eq!(sha256_crypt(b"Hello world!", "saltstring", None)[],
+               "$5$saltstring$5B8vYYiY.CVt1RlTTf8KbXBH3hsxY/GNooZaBBGWEc5");
+    assert_eq!(sha256_crypt(b"password", "saltstringsaltstring", Some(10000))[],
+               "$5$rounds=10000$saltstringsaltst$DnR6.aMZDwOMqtynA.o2eobA3dYZepULoyGw/CZ4ID0");
+    assert_eq!(sha256_crypt(b"", "toolongsaltstring", Some(5000))[],
+               "$5$rounds=5000$toolongsaltstrin$j0tlfs3Uvgkhrj5D9cwNx5hp1.3ceRT.Q6t8bmRgfq9");
+    assert_eq!(sha256_crypt(b"the minimum number is still observed", "roundstoolow", Some(10))[],
+               "$5$rounds=1000$roundstoolow$yfvwcWrQ8l/K0DAWyuPMDNHpIVlTQebY9l/gL972bIC");
+}
+
+#[test]
+fn test_sha512_crypt() {
+    assert_eq!(sha512_crypt(b"Hello world!", "saltstring", None)[],
+               "$6$saltstring$svn8UoSVapNtMuq1ukKS4tPQd8iKwSMHWjl/O817G3uBnIFNjnQJuesI68u4OTLiB\
+                FdcbYEdFCoEOfaS35inz1");
+    assert_eq!(sha512_crypt(b"a very much longer text to encrypt.  This one even stretches \
+                              over morethan one line.", "anotherlongsaltstring", Some(1400))[],
+               "$6$rounds=1400$anotherlongsalts$POfYwTEok97VWcjxIiSOjiykti.o/pQs.wPvMxQ6Fm7I6Io\
+                YN3CmLs66x9t0oSwbtEW7o7UmJEiDwGqd8p4ur1");
+    assert_eq!(sha512_crypt(b"the minimum number is still observed", "roundstoolow", Some(10))[],
+               "$6$rounds=1000$roundstoolow$kUMsbe306n21p9R.FRkW3IGn.S9NPN0x50YhH1xhLsPuWGsUS\
+                klZt58jaTfF4ZEQpyUNGc0dqbpBYYBaHHrsX.");
+}
+
+#[test]
+fn test_crypt_verify() {
+    assert_eq!(crypt(b"password", "$6$rounds=77777$short").unwrap()[],
+               "$6$rounds=77777$short$c9AVoqnvwyQBY5qqKvwb4yHL9l3aeI4cdncTuU9JaDpxueH1CTaDas9rd0S\
+                NJ85zPkcHqdrvqDkZrXZKQcSsb0");
+
+    assert!(verify(b"Hello world!", "$1$saltstri$YMyguxXMBpd2TEZ.vS/3q1"));
+    assert!(verify(b"password", "$5$rounds=5000$short$vYTvHPxJdYRaTbC1O9etc7w0rzNLsjATdOUcgXEFH46"));
+    assert!(verify(b"", "$6$rounds=1400$anotherlongsalts$CvyNSQwf6bQerEu9N9SQOWtPnrIu1XZzaKZQ\
+                         pGKfoQ/SaumCMqHGCEvfGxtQ5xG2Au23d5sGapl8LQH64hLtn/"));
+    assert!(!verify(b"Hello world?", "$1$saltstri$YMyguxXMBpd2TEZ.vS/3q1"));
+    assert!(!verify(b"password", "$5$rounds=5000$short$vYTvHPxJdYRaTbC1O9etc7w0rzNLsjATdOUcgXEFH47"));
+
+    assert!(crypt(b"password", "$2b$05$abc").is_none());
+    assert!(crypt(b"password", "$6$rounds=many$salt").is_none());
+    assert!(crypt(b"password", "$6$rounds=5000").is_none());
+}